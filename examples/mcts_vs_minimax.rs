@@ -0,0 +1,90 @@
+//! Plays Monte Carlo tree search against minimax from the starting position of each variant,
+//! alternating who moves first.
+//!
+//! cargo run --release --example mcts_vs_minimax -- [games per variant] [MCTS iterations] [minimax depth]
+use std::time::{Duration, Instant};
+
+use mancala_rs::game::{Board, Game, GameState, PlayerSide};
+use mancala_rs::mcts::MctsConfig;
+use mancala_rs::player::{MctsPlayer, MinimaxPlayer, Player};
+use mancala_rs::rules::Variant;
+
+/// Games that are still going after this many turns are counted as draws
+const MAX_TURNS: usize = 200;
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let arg = |i: usize, default: usize| {
+        args.get(i)
+            .and_then(|arg| arg.parse::<usize>().ok())
+            .unwrap_or(default)
+    };
+    let (games, iterations, depth) = (arg(0, 10), arg(1, 2000), arg(2, 2));
+    println!(
+        "MCTS ({} iterations) against minimax ({} turns), {} games per variant",
+        iterations, depth, games
+    );
+    for variant in Variant::ALL {
+        let (mut wins, mut losses, mut draws) = (0, 0, 0);
+        let (mut mcts_time, mut mcts_turns) = (Duration::ZERO, 0);
+        let (mut minimax_time, mut minimax_turns) = (Duration::ZERO, 0);
+        // minimax keeps its transposition table between games
+        let mut minimax = MinimaxPlayer::new(depth);
+        for round in 0..games {
+            // MCTS plays the Player in even rounds and the Opponent in odd ones
+            let mcts_side = match round % 2 {
+                0 => PlayerSide::Player,
+                _ => PlayerSide::Opponent,
+            };
+            let mut mcts = MctsPlayer::new(MctsConfig {
+                iterations,
+                seed: round as u64 + 1,
+                ..Default::default()
+            });
+            let mut game = Game::with_rules(Board::default(), variant.rules());
+            for _ in 0..MAX_TURNS {
+                if game.game_state != GameState::InProgress {
+                    break;
+                }
+                let start = Instant::now();
+                let turn = if game.board.player_turn == mcts_side {
+                    let sequence = mcts.choose_turn(&game);
+                    mcts_time += start.elapsed();
+                    mcts_turns += 1;
+                    sequence
+                } else {
+                    let sequence = minimax.choose_turn(&game);
+                    minimax_time += start.elapsed();
+                    minimax_turns += 1;
+                    sequence
+                };
+                let Ok(sequence) = turn else {
+                    break;
+                };
+                for pocket in sequence {
+                    game.play_move((pocket, game.board.player_turn))
+                        .expect("searches only return legal turns");
+                }
+            }
+            let mcts_result = game.player_result().map(|result| match mcts_side {
+                PlayerSide::Player => result,
+                PlayerSide::Opponent => 1.0 - result,
+            });
+            match mcts_result {
+                Some(result) if result > 0.5 => wins += 1,
+                Some(result) if result < 0.5 => losses += 1,
+                _ => draws += 1,
+            }
+        }
+        let per_turn = |time: Duration, turns: u32| time / turns.max(1);
+        println!(
+            "{}: MCTS {} wins, {} losses, {} draws; {:?} per MCTS turn, {:?} per minimax turn",
+            variant,
+            wins,
+            losses,
+            draws,
+            per_turn(mcts_time, mcts_turns),
+            per_turn(minimax_time, minimax_turns)
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Plays a round-robin tournament between the engines from balanced openings and prints the
+//! crosstable, followed by the result of every match.
+//! With the qlearning feature, a policy saved by train_qlearning can join the tournament.
+//!
+//! cargo run --release --example tournament -- [variant] [games per match] [policy file]
+use mancala_rs::game::{Board, Game};
+use mancala_rs::mcts::MctsConfig;
+use mancala_rs::player::{GreedyPlayer, MctsPlayer, MinimaxPlayer, Player, RandomPlayer};
+#[cfg(feature = "qlearning")]
+use mancala_rs::qlearning_move::QPolicy;
+use mancala_rs::rules::Variant;
+use mancala_rs::tournament::{round_robin, MatchConfig};
+
+fn main() -> Result<(), mancala_rs::error::Error> {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let variant = args
+        .first()
+        .and_then(|name| {
+            Variant::ALL
+                .into_iter()
+                .find(|variant| variant.to_string().eq_ignore_ascii_case(name))
+        })
+        .unwrap_or(Variant::Kalah);
+    let config = MatchConfig {
+        games: args.get(1).and_then(|arg| arg.parse().ok()).unwrap_or(20),
+        balanced_openings: true,
+        ..Default::default()
+    };
+    let mut players: Vec<Box<dyn Player>> = vec![
+        Box::new(RandomPlayer::new(1)),
+        Box::new(GreedyPlayer::new()),
+        Box::new(MinimaxPlayer::new(2)),
+        Box::new(MinimaxPlayer::new(4)),
+        Box::new(MctsPlayer::new(MctsConfig {
+            iterations: 1000,
+            ..Default::default()
+        })),
+    ];
+    #[cfg(feature = "qlearning")]
+    if let Some(path) = args.get(2) {
+        players.push(Box::new(QPolicy::load(path)?));
+    }
+    println!(
+        "{} players, {} games per match, {} rules",
+        players.len(),
+        config.games,
+        variant
+    );
+    let start = Game::with_rules(Board::default(), variant.rules());
+    let crosstable = round_robin(start, &mut players, &config);
+    println!("{}", crosstable);
+    for (i, row) in crosstable.results.iter().enumerate() {
+        for result in row.iter().skip(i + 1).flatten() {
+            println!("{}", result);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! Trains a Q-learning policy by self-play and saves it, continuing from the file if it exists.
+//! The policy is saved after every chunk of games, so a long run can be stopped at any time.
+//!
+//! cargo run --release --example train_qlearning -- [file] [variant] [pockets] [stones] [games] [games per save]
+use std::time::Instant;
+
+use mancala_rs::game::{Board, Game, DEFAULT_POCKETS, DEFAULT_STONES};
+use mancala_rs::qlearning_move::{QLearningConfig, QPolicy};
+use mancala_rs::rules::Variant;
+
+fn main() -> Result<(), mancala_rs::error::Error> {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let arg = |i: usize, default: usize| {
+        args.get(i)
+            .and_then(|arg| arg.parse::<usize>().ok())
+            .unwrap_or(default)
+    };
+    let path = args.first().map_or("policy.mqt", String::as_str);
+    let variant = args
+        .get(1)
+        .and_then(|name| {
+            Variant::ALL
+                .into_iter()
+                .find(|variant| variant.to_string().eq_ignore_ascii_case(name))
+        })
+        .unwrap_or(Variant::Kalah);
+    let board = Board::with_size(
+        arg(2, DEFAULT_POCKETS),
+        arg(3, DEFAULT_STONES as usize) as i32,
+    )?;
+    let (games, chunk) = (arg(4, 100000), arg(5, 10000).max(1));
+    let start = Game::with_rules(board, variant.rules());
+
+    let mut policy = match std::path::Path::new(path).exists() {
+        true => QPolicy::load(path)?,
+        false => QPolicy::new(),
+    };
+    println!(
+        "Training {} for {} games, starting from {} learned positions",
+        path,
+        games,
+        policy.len()
+    );
+    let began = Instant::now();
+    let mut played = 0;
+    while played < games {
+        let config = QLearningConfig {
+            episodes: chunk.min(games - played),
+            // each chunk plays different games, also when continuing a saved policy
+            seed: (policy.len() + played) as u64 + 1,
+            ..Default::default()
+        };
+        policy.train(start, &config);
+        played += config.episodes;
+        policy.save(path)?;
+        println!(
+            "{} games, {} positions, {:?}",
+            played,
+            policy.len(),
+            began.elapsed()
+        );
+    }
+    Ok(())
+}
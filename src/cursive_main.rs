@@ -1,21 +1,473 @@
-use crate::game::{Game, PlayerSide};
-use crate::solver::SequenceTree;
+use std::collections::VecDeque;
 
+use mancala_rs::eval::FeatureWeights;
+use mancala_rs::game::{
+    Board, Game, GameState, PlayerSide, PocketIndex, PocketLocation, TraceStep, DEFAULT_POCKETS,
+    DEFAULT_STONES, MAX_POCKETS,
+};
+use mancala_rs::minimax::GameTree;
+use mancala_rs::record::{pocket_notation, GameRecord};
+use mancala_rs::transposition::TranspositionTable;
+
+use cursive::views::{Dialog, EditView, LinearLayout, TextView};
 use cursive::{Cursive, CursiveExt};
-use cursive::views::{TextView};
-use cursive_core::theme::{BaseColor::*, Color::*, PaletteColor::*};
-use cursive_core::view::Nameable;
+use cursive_core::direction::Direction;
+use cursive_core::event::{Event, EventResult, Key, MouseButton, MouseEvent};
+use cursive_core::theme::{BaseColor::*, Color::*, Effect, PaletteColor::*};
+use cursive_core::view::{CannotFocus, Nameable, View};
+use cursive_core::{Printer, Vec2};
 
-pub mod game;
-pub mod solver;
+/// Width of a pocket on screen, e.g. " p2  4"
+const POCKET_WIDTH: usize = 6;
+/// Column of the Opponent's side pockets
+const OPPONENT_COLUMN: usize = 2 * POCKET_WIDTH;
+/// Column of the stores, halfway between the two sides
+const STORE_COLUMN: usize = POCKET_WIDTH;
+/// Turns searched ahead by "find best move" unless changed
+const DEFAULT_SEARCH_DEPTH: usize = 2;
+/// Deepest search allowed, deeper searches take minutes on the default board
+const MAX_SEARCH_DEPTH: usize = 6;
+/// Steps of a move shown per second
+const ANIMATION_FPS: u32 = 12;
 
-fn main() {
-    unsafe {
-        println!("{:?}", *std::ptr::null::<i32>()); 
+/// The board, drawn the same way as the CLI's display, along with the game record it shows.
+/// The selected pocket always belongs to the player whose turn it is.
+struct BoardView {
+    record: GameRecord,
+    stash: Option<GameRecord>,
+    /// The pocket that Enter plays
+    selected: PocketIndex,
+    /// Pockets on each side and stones in each pocket for new games
+    size: (usize, i32),
+    /// Turns searched ahead by "find best move"
+    search_depth: usize,
+    /// Evaluator used by "find best move"
+    weights: FeatureWeights,
+    /// Kept between searches so later searches reuse what earlier ones found. The search thread
+    /// holds it while a search runs.
+    table: TranspositionTable,
+    /// Whether a search is running in the background
+    searching: bool,
+    /// The last thing that happened, shown under the board
+    message: String,
+    /// Steps of the last move still to be shown, the board shown is the first step's
+    animation: VecDeque<TraceStep>,
+}
+
+impl BoardView {
+    fn new(game: Game) -> Self {
+        BoardView {
+            record: GameRecord::new(game),
+            stash: None,
+            selected: 0,
+            size: (DEFAULT_POCKETS, DEFAULT_STONES),
+            search_depth: DEFAULT_SEARCH_DEPTH,
+            weights: FeatureWeights::default(),
+            table: TranspositionTable::default(),
+            searching: false,
+            message: String::new(),
+            animation: VecDeque::new(),
+        }
+    }
+
+    fn game(&self) -> &Game {
+        self.record.current()
+    }
+
+    /// Replace the record, e.g. after a reset, and select the first pocket that can be played
+    fn set_record(&mut self, record: GameRecord) {
+        self.record = record;
+        self.animation.clear();
+        self.select_playable();
+    }
+
+    /// Keep the selection on a pocket that can be played, if there is one
+    fn select_playable(&mut self) {
+        let board = self.game().board;
+        let side = board.side_pockets(board.player_turn);
+        if self.selected >= side.len() || side[self.selected] == 0 {
+            self.selected = side.iter().position(|&stones| stones > 0).unwrap_or(0);
+        }
+    }
+
+    /// Play a pocket for the player to move and start animating it
+    fn play(&mut self, pocket: PocketIndex) {
+        let side = self.game().board.player_turn;
+        match self.record.play_move_traced(pocket) {
+            Ok(steps) => {
+                self.animation = steps.into();
+                self.message = format!("Played {}", pocket_notation((pocket, side)));
+                self.selected = pocket;
+                self.select_playable();
+            }
+            Err(error) => self.message = format!("Invalid move: {}", error),
+        }
+    }
+
+    /// Move the selection one pocket up or down the screen
+    fn move_selection(&mut self, down: bool) {
+        let pockets = self.game().board.pockets();
+        // the Player's pockets are numbered down the screen, the Opponent's up it
+        let forward = match self.game().board.player_turn {
+            PlayerSide::Player => down,
+            PlayerSide::Opponent => !down,
+        };
+        self.selected = match forward {
+            true => (self.selected + 1).min(pockets - 1),
+            false => self.selected.saturating_sub(1),
+        };
+    }
+
+    /// Where a pocket is drawn, as the position of its first character
+    fn pocket_position(&self, pocket: PocketLocation) -> Vec2 {
+        let board = self.game().board;
+        let store = board.store();
+        match pocket {
+            (pocket, PlayerSide::Opponent) if pocket == store => Vec2::new(STORE_COLUMN, 0),
+            (pocket, PlayerSide::Player) if pocket == store => {
+                Vec2::new(STORE_COLUMN, board.pockets() + 1)
+            }
+            (pocket, PlayerSide::Player) => Vec2::new(0, pocket + 1),
+            (pocket, PlayerSide::Opponent) => Vec2::new(OPPONENT_COLUMN, board.pockets() - pocket),
+        }
     }
-    let game = Game::new();
-    //let mut tree = SequenceTree::new(game.clone());
-    //tree.generate_tree(PlayerSide::Player, PlayerSide::Player, 0);
+
+    /// The side pocket drawn at a position on screen, if any
+    fn pocket_at(&self, position: Vec2) -> Option<PocketLocation> {
+        let board = self.game().board;
+        [PlayerSide::Player, PlayerSide::Opponent]
+            .into_iter()
+            .flat_map(|side| (0..board.pockets()).map(move |pocket| (pocket, side)))
+            .find(|&pocket| {
+                let start = self.pocket_position(pocket);
+                position.y == start.y && (start.x..start.x + POCKET_WIDTH).contains(&position.x)
+            })
+    }
+
+    /// Turn and game state shown under the board
+    fn status(&self) -> String {
+        if let Some(step) = self.animation.front() {
+            return format!("{}\nPosition: {}\n(Enter) Skip", step.event, step.board);
+        }
+        let game = self.game();
+        let state = match game.game_state {
+            GameState::InProgress => format!("{}'s turn", game.board.player_turn),
+            GameState::Over(game_over) => format!("Game over: {:?}", game_over),
+        };
+        let search = match self.searching {
+            true => "searching...".to_string(),
+            false => format!("search depth {}", self.search_depth),
+        };
+        format!(
+            "{}, {} rules, {}\nPosition: {}\n{}",
+            state,
+            game.rules
+                .variant()
+                .map_or("custom".to_string(), |variant| variant.to_string()),
+            search,
+            game.board,
+            self.message
+        )
+    }
+}
+
+impl View for BoardView {
+    fn draw(&self, printer: &Printer) {
+        // while a move is animated, the pocket of the current step is highlighted instead
+        let (board, highlighted) = match self.animation.front() {
+            Some(step) => (step.board, step.event.pocket()),
+            None => {
+                let board = self.game().board;
+                let playing = self.game().game_state == GameState::InProgress;
+                (board, playing.then_some((self.selected, board.player_turn)))
+            }
+        };
+        for side in [PlayerSide::Player, PlayerSide::Opponent] {
+            for pocket in 0..=board.store() {
+                let location = (pocket, side);
+                let text = format!(
+                    "{:>3} {:>2}",
+                    pocket_notation(location),
+                    board.get_stones(location)
+                );
+                let position = self.pocket_position(location);
+                if highlighted == Some(location) {
+                    printer.with_effect(Effect::Reverse, |printer| printer.print(position, &text));
+                } else {
+                    printer.print(position, &text);
+                }
+            }
+        }
+    }
+
+    fn required_size(&mut self, _: Vec2) -> Vec2 {
+        Vec2::new(
+            OPPONENT_COLUMN + POCKET_WIDTH,
+            self.game().board.pockets() + 2,
+        )
+    }
+
+    fn take_focus(&mut self, _: Direction) -> Result<EventResult, CannotFocus> {
+        Ok(EventResult::Consumed(None))
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        if !self.animation.is_empty() {
+            match event {
+                // refreshes come ANIMATION_FPS times a second
+                Event::Refresh => {
+                    self.animation.pop_front();
+                }
+                Event::Key(Key::Enter) | Event::Char(' ') => self.animation.clear(),
+                _ => return EventResult::Ignored,
+            }
+            return EventResult::with_cb(refresh_status);
+        }
+        match event {
+            Event::Key(Key::Up) => self.move_selection(false),
+            Event::Key(Key::Down) => self.move_selection(true),
+            Event::Key(Key::Enter) | Event::Char(' ') => self.play(self.selected),
+            Event::Char(digit @ '0'..='9') => self.play(digit as usize - '0' as usize),
+            Event::Mouse {
+                offset,
+                position,
+                event: MouseEvent::Press(MouseButton::Left),
+            } => {
+                let pocket = position
+                    .checked_sub(offset)
+                    .and_then(|position| self.pocket_at(position));
+                match pocket {
+                    Some((pocket, side)) if side == self.game().board.player_turn => {
+                        self.play(pocket)
+                    }
+                    _ => return EventResult::Ignored,
+                }
+            }
+            _ => return EventResult::Ignored,
+        }
+        EventResult::with_cb(refresh_status)
+    }
+}
+
+/// Show the board view's status in the status text
+fn refresh_status(s: &mut Cursive) {
+    let status = s
+        .call_on_name("board", |view: &mut BoardView| view.status())
+        .unwrap_or_default();
+    s.call_on_name("status", |view: &mut TextView| view.set_content(status));
+}
+
+/// Run a callback on the board view, then show its new status
+fn with_board(s: &mut Cursive, f: impl FnOnce(&mut BoardView)) {
+    s.call_on_name("board", f);
+    refresh_status(s);
+}
+
+/// Search for the best turn on a separate thread so the UI keeps responding, with the same
+/// evaluator and transposition table as the CLI's search.
+/// The result is only shown if the game hasn't changed since the search started.
+fn find_best_move(s: &mut Cursive) {
+    let search = s.call_on_name("board", |view: &mut BoardView| {
+        if view.searching || view.game().game_state != GameState::InProgress {
+            return None;
+        }
+        view.searching = true;
+        view.message = format!("Searching {} turns ahead...", view.search_depth);
+        let table = std::mem::take(&mut view.table);
+        Some((*view.game(), view.search_depth, view.weights, table))
+    });
+    refresh_status(s);
+    let Some(Some((game, depth, weights, mut table))) = search else {
+        return;
+    };
+    let sink = s.cb_sink().clone();
+    std::thread::spawn(move || {
+        let result = GameTree::with_evaluator(game, weights).search(depth, &mut table);
+        // the UI may have quit while searching, in which case there is nothing left to update
+        let _ = sink.send(Box::new(move |s: &mut Cursive| {
+            with_board(s, |view| {
+                view.searching = false;
+                view.table = table;
+                if *view.game() != game {
+                    view.message = "The game changed during the search".to_string();
+                    return;
+                }
+                let result = match result {
+                    Ok(result) => result,
+                    Err(error) => {
+                        view.message = error.to_string();
+                        return;
+                    }
+                };
+                let side = game.board.player_turn;
+                let turn = result
+                    .sequence
+                    .iter()
+                    .map(|&pocket| pocket_notation((pocket, side)))
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                view.message = format!("Best turn: {} (value {})", turn, result.value);
+                view.selected = result.sequence[0];
+            });
+        }));
+    });
+}
+
+/// Ask for a position and start a new game from it with the current rules
+fn enter_position(s: &mut Cursive) {
+    let submit = |s: &mut Cursive, text: &str| match text.parse::<Board>() {
+        Ok(board) => {
+            s.pop_layer();
+            with_board(s, |view| {
+                let rules = view.game().rules;
+                view.set_record(GameRecord::new(Game::with_rules(board, rules)));
+                view.message = "Entered position".to_string();
+            });
+        }
+        Err(error) => {
+            s.add_layer(Dialog::info(format!("Invalid position: {}", error)));
+        }
+    };
+    let edit = EditView::new().on_submit(submit).with_name("position");
+    s.add_layer(
+        Dialog::around(edit)
+            .title("Enter the position, e.g. 5,3,2,0,1,7/12 7,2,4,9,0,2/9 P")
+            .button("Ok", move |s| {
+                let text = s
+                    .call_on_name("position", |view: &mut EditView| view.get_content())
+                    .unwrap_or_default();
+                submit(s, &text);
+            })
+            .dismiss_button("Cancel"),
+    );
+}
+
+/// Ask for a ply and show the game after that many moves of the record
+fn jump_to_ply(s: &mut Cursive) {
+    let submit = |s: &mut Cursive, text: &str| {
+        let Ok(ply) = text.trim().parse::<usize>() else {
+            s.add_layer(Dialog::info("Invalid input"));
+            return;
+        };
+        s.pop_layer();
+        with_board(s, |view| {
+            view.animation.clear();
+            view.message = match view.record.jump_to(ply) {
+                true => format!("Jumped to move {}", ply),
+                false => format!("The game only has {} moves", view.record.entries().len()),
+            };
+            view.select_playable();
+        });
+    };
+    let edit = EditView::new().on_submit(submit).with_name("ply");
+    s.add_layer(
+        Dialog::around(edit)
+            .title("Enter the number of moves to jump to, 0 for the start")
+            .button("Ok", move |s| {
+                let text = s
+                    .call_on_name("ply", |view: &mut EditView| view.get_content())
+                    .unwrap_or_default();
+                submit(s, &text);
+            })
+            .dismiss_button("Cancel"),
+    );
+}
+
+/// Ask for the board size and start a new game of that size with the current rules
+fn change_board_size(s: &mut Cursive) {
+    let submit = |s: &mut Cursive, text: &str| {
+        let fields = text.split_whitespace().collect::<Vec<&str>>();
+        let size = match fields.as_slice() {
+            [pockets, stones] => match (pockets.parse::<usize>(), stones.parse::<i32>()) {
+                (Ok(pockets), Ok(stones)) => (pockets, stones),
+                _ => {
+                    s.add_layer(Dialog::info("Invalid input"));
+                    return;
+                }
+            },
+            _ => {
+                s.add_layer(Dialog::info("Invalid input"));
+                return;
+            }
+        };
+        let board = match Board::with_size(size.0, size.1) {
+            Ok(board) => board,
+            Err(error) => {
+                s.add_layer(Dialog::info(format!("Invalid board: {}", error)));
+                return;
+            }
+        };
+        s.pop_layer();
+        with_board(s, |view| {
+            view.size = size;
+            let rules = view.game().rules;
+            view.set_record(GameRecord::new(Game::with_rules(board, rules)));
+            view.message = format!("New game with {} pockets of {} stones", size.0, size.1);
+        });
+    };
+    let edit = EditView::new().on_submit(submit).with_name("size");
+    s.add_layer(
+        Dialog::around(edit)
+            .title(format!(
+                "Enter the number of pockets on each side (1 to {}) and the stones in each pocket",
+                MAX_POCKETS
+            ))
+            .button("Ok", move |s| {
+                let text = s
+                    .call_on_name("size", |view: &mut EditView| view.get_content())
+                    .unwrap_or_default();
+                submit(s, &text);
+            })
+            .dismiss_button("Cancel"),
+    );
+}
+
+/// Ask for a weights file, as saved by the CLI's Tu(n)e Weights, for "find best move" to use
+fn load_weights(s: &mut Cursive) {
+    let submit = |s: &mut Cursive, text: &str| {
+        let path = text.trim();
+        let weights = match std::fs::read_to_string(path) {
+            Ok(text) => text.parse::<FeatureWeights>(),
+            Err(error) => {
+                s.add_layer(Dialog::info(format!("Couldn't open weights: {}", error)));
+                return;
+            }
+        };
+        let weights = match weights {
+            Ok(weights) => weights,
+            Err(error) => {
+                s.add_layer(Dialog::info(format!("Couldn't read weights: {}", error)));
+                return;
+            }
+        };
+        s.pop_layer();
+        with_board(s, |view| {
+            if view.searching {
+                view.message = "Wait for the search to finish before changing weights".to_string();
+                return;
+            }
+            view.weights = weights;
+            // cached values were found with the old weights
+            view.table = TranspositionTable::default();
+            view.message = format!("Loaded weights from {}", path);
+        });
+    };
+    let edit = EditView::new().on_submit(submit).with_name("weights");
+    s.add_layer(
+        Dialog::around(edit)
+            .title("Enter the weights file to load")
+            .button("Ok", move |s| {
+                let text = s
+                    .call_on_name("weights", |view: &mut EditView| view.get_content())
+                    .unwrap_or_default();
+                submit(s, &text);
+            })
+            .dismiss_button("Cancel"),
+    );
+}
+
+fn main() {
+    let game = Game::default();
 
     let mut siv = Cursive::default();
     // disable shadows, set background to black, and text to white
@@ -25,46 +477,96 @@ fn main() {
         theme.palette[View] = Dark(Black);
         theme.palette[Primary] = Light(White);
     });
-    // Main menu has the following options:
-    // - Reset Game
-    // - Manually Enter Board State
-    // - Stash Game
-    // - Load Game
-    // - Test move (displays resulting board)
-    // - Play move
-    // - Generate Sequence Tree
-    // - Solve Game (not yet implemented)
-
-    siv.add_layer(TextView::new(format!("{:?}", game)).with_name("board_text"));
-
-    let mut main_menu = cursive::views::LinearLayout::vertical();
-    main_menu.add_child(TextView::new("Main Menu").with_name("main_menu_title"));
-    main_menu.add_child(TextView::new("(R)eset Game").with_name("reset_game"));
-    main_menu.add_child(TextView::new("(M)anually Enter Board State").with_name("enter_board_state"));
-    main_menu.add_child(TextView::new("(S)tash Game").with_name("stash_game"));
-    main_menu.add_child(TextView::new("(L)oad Game").with_name("load_game"));
-    main_menu.add_child(TextView::new("(T)est Move").with_name("test_move"));
-    main_menu.add_child(TextView::new("(P)lay Move").with_name("play_move"));
-    main_menu.add_child(TextView::new("(G)enerate Sequence Tree").with_name("generate_sequence_tree"));
-    main_menu.add_child(TextView::new("(F)ind best move").with_name("solve_game"));
-    siv.add_layer(main_menu.with_name("main_menu"));
 
-    // add menu callbacks
-    siv.add_global_callback('r', move |s| {
-        s.call_on_name("board_text", |view: &mut TextView| {
-            view.set_content(format!("{:?}", game));
-        });
-    });
+    let board_view = BoardView::new(game);
+    let status = board_view.status();
 
-    siv.add_global_callback('m', |s| {
-        let game = Game::new();
-        s.call_on_name("board_text", |view: &mut TextView| {
-            view.set_content(format!("{:?}", game));
-        });
-    });
+    let mut main_menu = LinearLayout::vertical();
+    main_menu.add_child(TextView::new("Main Menu"));
+    main_menu.add_child(TextView::new("(Up/Down) Select pocket"));
+    main_menu.add_child(TextView::new("(Enter) Play selected pocket"));
+    main_menu.add_child(TextView::new("(0-7) Play pocket"));
+    main_menu.add_child(TextView::new("(R)eset Game"));
+    main_menu.add_child(TextView::new("(M)anually Enter Board State"));
+    main_menu.add_child(TextView::new("(B)oard Size"));
+    main_menu.add_child(TextView::new("(S)tash Game"));
+    main_menu.add_child(TextView::new("(L)oad Game"));
+    main_menu.add_child(TextView::new("(U)ndo Move"));
+    main_menu.add_child(TextView::new("R(e)do Move"));
+    main_menu.add_child(TextView::new("(J)ump to Move"));
+    main_menu.add_child(TextView::new("(F)ind best move"));
+    main_menu.add_child(TextView::new("(+/-) Change search depth"));
+    main_menu.add_child(TextView::new("(W)eights"));
+    main_menu.add_child(TextView::new("(Q)uit"));
 
+    let mut layout = LinearLayout::vertical();
+    layout.add_child(board_view.with_name("board"));
+    layout.add_child(TextView::new(status).with_name("status"));
+    let mut screen = LinearLayout::horizontal();
+    screen.add_child(Dialog::around(layout).title("Mancala"));
+    screen.add_child(Dialog::around(main_menu));
+    siv.add_layer(screen);
 
+    // add menu callbacks
+    siv.add_global_callback('r', |s| {
+        with_board(s, |view| {
+            let rules = view.game().rules;
+            let board = Board::with_size(view.size.0, view.size.1)
+                .expect("the board size was checked when it was set");
+            view.set_record(GameRecord::new(Game::with_rules(board, rules)));
+            view.message = "Reset game".to_string();
+        })
+    });
+    siv.add_global_callback('m', enter_position);
+    siv.add_global_callback('b', change_board_size);
+    siv.add_global_callback('s', |s| {
+        with_board(s, |view| {
+            view.stash = Some(view.record.clone());
+            view.message = "Stashed game".to_string();
+        })
+    });
+    siv.add_global_callback('l', |s| {
+        with_board(s, |view| match view.stash.clone() {
+            Some(stash) => {
+                view.set_record(stash);
+                view.message = "Loaded game".to_string();
+            }
+            None => view.message = "No game stashed".to_string(),
+        })
+    });
+    siv.add_global_callback('u', |s| {
+        with_board(s, |view| {
+            view.animation.clear();
+            view.message = match view.record.undo() {
+                true => "Undid move".to_string(),
+                false => "No moves to undo".to_string(),
+            };
+            view.select_playable();
+        })
+    });
+    siv.add_global_callback('e', |s| {
+        with_board(s, |view| {
+            view.animation.clear();
+            view.message = match view.record.redo() {
+                true => "Redid move".to_string(),
+                false => "No moves to redo".to_string(),
+            };
+            view.select_playable();
+        })
+    });
+    siv.add_global_callback('j', jump_to_ply);
+    siv.add_global_callback('f', find_best_move);
+    siv.add_global_callback('+', |s| {
+        with_board(s, |view| {
+            view.search_depth = (view.search_depth + 1).min(MAX_SEARCH_DEPTH)
+        })
+    });
+    siv.add_global_callback('-', |s| {
+        with_board(s, |view| view.search_depth = (view.search_depth - 1).max(1))
+    });
+    siv.add_global_callback('w', load_weights);
     siv.add_global_callback('q', |s| s.quit());
 
+    siv.set_fps(ANIMATION_FPS);
     siv.run();
 }
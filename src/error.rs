@@ -0,0 +1,110 @@
+use std::fmt::Display;
+
+use crate::eval::ParseWeightsError;
+use crate::game::{InvalidBoardError, InvalidPocketError};
+use crate::position::ParsePositionError;
+#[cfg(feature = "qlearning")]
+use crate::qlearning_move::QTableError;
+use crate::record::ParseRecordError;
+use crate::solver::SequenceTreeIndex;
+
+/// Any error the crate can return. Each module's own error type converts into it, so callers
+/// that don't care where an error came from can use `?` across the whole crate.
+#[derive(Debug)]
+pub enum Error {
+    /// A move that can't be played
+    InvalidMove(InvalidPocketError),
+    /// A board that can't be played on
+    InvalidBoard(InvalidBoardError),
+    /// A position string that can't be read
+    InvalidPosition(ParsePositionError),
+    /// A game record that can't be read
+    InvalidRecord(ParseRecordError),
+    /// A weights file that can't be read
+    InvalidWeights(ParseWeightsError),
+    /// A Q-table that can't be saved or loaded
+    #[cfg(feature = "qlearning")]
+    InvalidQTable(QTableError),
+    /// Reading or writing a file or the terminal failed
+    Io(std::io::Error),
+    /// A sequence tree index that doesn't refer to a node in the tree
+    InvalidNode(SequenceTreeIndex),
+    /// There is no turn to choose, because the game is over or the tree was never generated
+    NoMoves,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidMove(error) => write!(f, "invalid move: {}", error),
+            Error::InvalidBoard(error) => write!(f, "invalid board: {}", error),
+            Error::InvalidPosition(error) => write!(f, "invalid position: {}", error),
+            Error::InvalidRecord(error) => write!(f, "invalid record: {}", error),
+            Error::InvalidWeights(error) => write!(f, "invalid weights: {}", error),
+            #[cfg(feature = "qlearning")]
+            Error::InvalidQTable(error) => write!(f, "invalid Q-table: {}", error),
+            Error::Io(error) => write!(f, "{}", error),
+            Error::InvalidNode(index) => write!(f, "no sequence tree node at index {}", index),
+            Error::NoMoves => write!(f, "there are no moves to play"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidMove(error) => Some(error),
+            Error::InvalidBoard(error) => Some(error),
+            Error::InvalidPosition(error) => Some(error),
+            Error::InvalidRecord(error) => Some(error),
+            Error::InvalidWeights(error) => Some(error),
+            #[cfg(feature = "qlearning")]
+            Error::InvalidQTable(error) => Some(error),
+            Error::Io(error) => Some(error),
+            Error::InvalidNode(_) | Error::NoMoves => None,
+        }
+    }
+}
+
+impl From<InvalidPocketError> for Error {
+    fn from(error: InvalidPocketError) -> Self {
+        Error::InvalidMove(error)
+    }
+}
+
+impl From<InvalidBoardError> for Error {
+    fn from(error: InvalidBoardError) -> Self {
+        Error::InvalidBoard(error)
+    }
+}
+
+impl From<ParsePositionError> for Error {
+    fn from(error: ParsePositionError) -> Self {
+        Error::InvalidPosition(error)
+    }
+}
+
+impl From<ParseRecordError> for Error {
+    fn from(error: ParseRecordError) -> Self {
+        Error::InvalidRecord(error)
+    }
+}
+
+impl From<ParseWeightsError> for Error {
+    fn from(error: ParseWeightsError) -> Self {
+        Error::InvalidWeights(error)
+    }
+}
+
+#[cfg(feature = "qlearning")]
+impl From<QTableError> for Error {
+    fn from(error: QTableError) -> Self {
+        Error::InvalidQTable(error)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error)
+    }
+}
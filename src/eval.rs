@@ -0,0 +1,292 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::game::{Game, PlayerSide};
+use crate::rules::Sowing;
+
+/*
+Weights files list one feature per line, written as its name and its weight. Features that are left
+out have a weight of 0, and lines starting with ';' are ignored. The default weights are written as
+StoreDifference 1
+StonesOnSide 0
+Mobility 0
+FreeMovePotential 0
+*/
+
+/// Scores a position statically, without searching ahead.
+/// Positive values favor the Player and negative values favor the Opponent, no matter whose turn
+/// it is. Any `Fn(&Game) -> f32` is also an evaluator.
+pub trait Evaluator {
+    fn evaluate(&self, game: &Game) -> f32;
+}
+
+impl<F: Fn(&Game) -> f32> Evaluator for F {
+    fn evaluate(&self, game: &Game) -> f32 {
+        self(game)
+    }
+}
+
+/// The difference between the stores, which is the final score once the game is over
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StoreDifference;
+
+impl Evaluator for StoreDifference {
+    fn evaluate(&self, game: &Game) -> f32 {
+        game.board.score(PlayerSide::Player) as f32 - game.board.score(PlayerSide::Opponent) as f32
+    }
+}
+
+/// The difference between the stones left in each side's pockets.
+/// With rules that sweep the remaining stones to their owner, these are points still to come.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StonesOnSide;
+
+impl Evaluator for StonesOnSide {
+    fn evaluate(&self, game: &Game) -> f32 {
+        let stones = |side| game.board.side_pockets(side).iter().sum::<i32>() as f32;
+        stones(PlayerSide::Player) - stones(PlayerSide::Opponent)
+    }
+}
+
+/// The difference between the number of pockets each side could play
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mobility;
+
+impl Evaluator for Mobility {
+    fn evaluate(&self, game: &Game) -> f32 {
+        let playable = |side| {
+            game.board
+                .side_pockets(side)
+                .iter()
+                .filter(|&&stones| stones > 0)
+                .count() as f32
+        };
+        playable(PlayerSide::Player) - playable(PlayerSide::Opponent)
+    }
+}
+
+/// The difference between the number of pockets on each side whose last stone would land in
+/// that side's store, earning a free turn. Always 0 under rules without free turns.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FreeMovePotential;
+
+impl Evaluator for FreeMovePotential {
+    fn evaluate(&self, game: &Game) -> f32 {
+        if !game.rules.free_turn || game.rules.sowing != Sowing::Store {
+            return 0.0;
+        }
+        let store = game.board.store();
+        // a lap around the board passes every side pocket and the mover's own store
+        let lap = 2 * game.board.pockets() + 1;
+        let free_moves = |side| {
+            game.board
+                .side_pockets(side)
+                .iter()
+                .enumerate()
+                .filter(|&(pocket, &stones)| stones > 0 && stones as usize % lap == store - pocket)
+                .count() as f32
+        };
+        free_moves(PlayerSide::Player) - free_moves(PlayerSide::Opponent)
+    }
+}
+
+/// A weighted sum of other evaluators
+///
+/// ```
+/// use mancala_rs::eval::{Evaluator, Mobility, StoreDifference, Weighted};
+/// let evaluator = Weighted::new().with(1.0, StoreDifference).with(0.25, Mobility);
+/// assert_eq!(evaluator.evaluate(&Default::default()), 0.0);
+/// ```
+#[derive(Default)]
+pub struct Weighted {
+    terms: Vec<(f32, Box<dyn Evaluator + Send + Sync>)>,
+}
+
+impl Weighted {
+    pub fn new() -> Self {
+        Weighted { terms: Vec::new() }
+    }
+
+    /// Add an evaluator to the sum, multiplied by `weight`
+    pub fn with(mut self, weight: f32, evaluator: impl Evaluator + Send + Sync + 'static) -> Self {
+        self.terms.push((weight, Box::new(evaluator)));
+        self
+    }
+
+    /// The weight of each evaluator in the sum, in the order they were added
+    pub fn weights(&self) -> Vec<f32> {
+        self.terms.iter().map(|(weight, _)| *weight).collect()
+    }
+}
+
+impl Evaluator for Weighted {
+    fn evaluate(&self, game: &Game) -> f32 {
+        self.terms
+            .iter()
+            .map(|(weight, evaluator)| weight * evaluator.evaluate(game))
+            .sum()
+    }
+}
+
+/// The built-in evaluators that can be weighted with `FeatureWeights`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    StoreDifference,
+    StonesOnSide,
+    Mobility,
+    FreeMovePotential,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 4] = [
+        Feature::StoreDifference,
+        Feature::StonesOnSide,
+        Feature::Mobility,
+        Feature::FreeMovePotential,
+    ];
+}
+
+impl Display for Feature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Feature::StoreDifference => write!(f, "StoreDifference"),
+            Feature::StonesOnSide => write!(f, "StonesOnSide"),
+            Feature::Mobility => write!(f, "Mobility"),
+            Feature::FreeMovePotential => write!(f, "FreeMovePotential"),
+        }
+    }
+}
+
+impl Evaluator for Feature {
+    fn evaluate(&self, game: &Game) -> f32 {
+        match self {
+            Feature::StoreDifference => StoreDifference.evaluate(game),
+            Feature::StonesOnSide => StonesOnSide.evaluate(game),
+            Feature::Mobility => Mobility.evaluate(game),
+            Feature::FreeMovePotential => FreeMovePotential.evaluate(game),
+        }
+    }
+}
+
+/// A weighted sum of the built-in features that can be saved to and loaded from a weights file.
+/// Unlike `Weighted`, it is `Copy`, so it can be handed to every search without boxing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeatureWeights {
+    /// The weight of each feature, in the order of `Feature::ALL`
+    weights: [f32; Feature::ALL.len()],
+}
+
+impl Default for FeatureWeights {
+    /// Only the store difference, which evaluates the same as `StoreDifference`
+    fn default() -> Self {
+        let mut weights = FeatureWeights::zero();
+        weights.set(Feature::StoreDifference, 1.0);
+        weights
+    }
+}
+
+impl FeatureWeights {
+    /// Every feature weighted 0
+    pub fn zero() -> Self {
+        FeatureWeights {
+            weights: [0.0; Feature::ALL.len()],
+        }
+    }
+
+    pub fn get(&self, feature: Feature) -> f32 {
+        self.weights[feature as usize]
+    }
+
+    pub fn set(&mut self, feature: Feature, weight: f32) {
+        self.weights[feature as usize] = weight;
+    }
+
+    /// The value of every feature for the game, in the order of `Feature::ALL`
+    pub fn features(game: &Game) -> [f32; Feature::ALL.len()] {
+        Feature::ALL.map(|feature| feature.evaluate(game))
+    }
+}
+
+impl Evaluator for FeatureWeights {
+    fn evaluate(&self, game: &Game) -> f32 {
+        Feature::ALL
+            .iter()
+            .zip(self.weights)
+            // unused features are skipped, since they are the slowest part of the evaluation
+            .filter(|(_, weight)| *weight != 0.0)
+            .map(|(feature, weight)| weight * feature.evaluate(game))
+            .sum()
+    }
+}
+
+/// Error type for reading a weights file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWeightsError {
+    /// A line is not a feature name followed by a weight
+    MalformedLine(String),
+    /// The feature is not one of the built-in features
+    UnknownFeature(String),
+    /// The weight is not a number
+    InvalidWeight(String),
+    /// The feature was given a weight more than once
+    DuplicateFeature(Feature),
+}
+
+impl Display for ParseWeightsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseWeightsError::MalformedLine(line) => {
+                write!(f, "a line should be a feature and its weight: {}", line)
+            }
+            ParseWeightsError::UnknownFeature(feature) => {
+                write!(f, "unknown feature: {}", feature)
+            }
+            ParseWeightsError::InvalidWeight(weight) => write!(f, "invalid weight: {}", weight),
+            ParseWeightsError::DuplicateFeature(feature) => {
+                write!(f, "{} is weighted more than once", feature)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseWeightsError {}
+
+impl Display for FeatureWeights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for feature in Feature::ALL {
+            writeln!(f, "{} {}", feature, self.get(feature))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for FeatureWeights {
+    type Err = ParseWeightsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = FeatureWeights::zero();
+        let mut seen = Vec::new();
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            let [name, weight] = fields.as_slice() else {
+                return Err(ParseWeightsError::MalformedLine(line.to_string()));
+            };
+            let feature = Feature::ALL
+                .into_iter()
+                .find(|feature| feature.to_string().eq_ignore_ascii_case(name))
+                .ok_or_else(|| ParseWeightsError::UnknownFeature(name.to_string()))?;
+            if seen.contains(&feature) {
+                return Err(ParseWeightsError::DuplicateFeature(feature));
+            }
+            seen.push(feature);
+            let weight = weight
+                .parse::<f32>()
+                .map_err(|_| ParseWeightsError::InvalidWeight(weight.to_string()))?;
+            weights.set(feature, weight);
+        }
+        Ok(weights)
+    }
+}
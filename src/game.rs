@@ -1,9 +1,21 @@
 use std::cmp::Ordering;
 use std::fmt::{Debug, Display};
+use std::hash::{Hash, Hasher};
+
+use crate::record::pocket_notation;
+use crate::rules::{Capture, GameEnd, Rules, Sowing, Sweep};
+use crate::zobrist::{opponent_turn_key, pocket_key};
 
 pub type PocketIndex = usize;
 pub type PocketLocation = (PocketIndex, PlayerSide);
 
+/// The largest number of side pockets (not counting the store) a board can have on each side
+pub const MAX_POCKETS: usize = 8;
+/// Number of side pockets on each side of the default board
+pub const DEFAULT_POCKETS: usize = 6;
+/// Number of stones in each side pocket of the default board
+pub const DEFAULT_STONES: i32 = 4;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum PlayerSide {
     Player,
@@ -27,20 +39,34 @@ impl Display for PlayerSide {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+/// A board with any number of side pockets per side, up to `MAX_POCKETS`.
+/// Each side's pockets are numbered from 0 going counter-clockwise, and the store comes right after
+/// the last side pocket (index 6 on the default board). Entries after the store are always empty.
+///
+/// The pockets are public for reading, changing them directly leaves the Zobrist key out of date,
+/// so boards should only be built through `Board::new` or `Board::free_form` and changed by
+/// playing moves.
+#[derive(Copy, Clone, PartialEq, Eq)]
 pub struct Board {
-    pub player_pockets: [i32; 7],
-    pub opponent_pockets: [i32; 7],
+    pub player_pockets: [i32; MAX_POCKETS + 1],
+    pub opponent_pockets: [i32; MAX_POCKETS + 1],
     pub player_turn: PlayerSide, // either Player or Opponent
+    /// Number of side pockets on each side, which is also the index of the stores
+    pockets: usize,
+    /// Zobrist key of the board, kept up to date as stones are moved
+    zobrist_key: u64,
+}
+
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // The key already identifies the pockets and the turn
+        state.write_u64(self.zobrist_key);
+    }
 }
 
 impl Default for Board {
     fn default() -> Self {
-        Board {
-            player_pockets: [4, 4, 4, 4, 4, 4, 0],
-            opponent_pockets: [4, 4, 4, 4, 4, 4, 0],
-            player_turn: PlayerSide::Player,
-        }
+        Board::with_size(DEFAULT_POCKETS, DEFAULT_STONES).expect("the default board is valid")
     }
 }
 
@@ -49,25 +75,163 @@ impl Default for Game {
         Game {
             board: Board::default(),
             game_state: GameState::InProgress,
+            rules: Rules::default(),
         }
     }
 }
 
 impl Board {
+    /// Create a board from the stones in each side's pockets, with the store as the last entry,
+    /// for a game that started with `stones` stones in each side pocket.
+    /// Besides the checks made by `Board::free_form`, the board must hold exactly the stones the
+    /// game started with, so a mistyped pocket is caught before it reaches the solver.
     pub fn new(
-        player_pockets: [i32; 7],
-        opponent_pockets: [i32; 7],
+        player_pockets: &[i32],
+        opponent_pockets: &[i32],
         player_turn: PlayerSide,
-    ) -> Self {
-        Board {
-            player_pockets,
-            opponent_pockets,
+        stones: i32,
+    ) -> Result<Self, InvalidBoardError> {
+        let board = Board::free_form(player_pockets, opponent_pockets, player_turn)?;
+        board.check_total(stones)?;
+        Ok(board)
+    }
+
+    /// Create a board from the stones in each side's pockets without checking the total, for
+    /// puzzle positions that can't be reached from a starting board.
+    /// Both sides must have the same number of pockets, at most `MAX_POCKETS` side pockets, and
+    /// no pocket or store can hold fewer than 0 stones.
+    pub fn free_form(
+        player_pockets: &[i32],
+        opponent_pockets: &[i32],
+        player_turn: PlayerSide,
+    ) -> Result<Self, InvalidBoardError> {
+        if player_pockets.len() != opponent_pockets.len() {
+            return Err(InvalidBoardError::MismatchedSides(
+                player_pockets.len().saturating_sub(1),
+                opponent_pockets.len().saturating_sub(1),
+            ));
+        }
+        if !(2..=MAX_POCKETS + 1).contains(&player_pockets.len()) {
+            return Err(InvalidBoardError::InvalidPocketCount(
+                player_pockets.len().saturating_sub(1),
+            ));
+        }
+        for (side, pockets) in [
+            (PlayerSide::Player, player_pockets),
+            (PlayerSide::Opponent, opponent_pockets),
+        ] {
+            if let Some(pocket) = pockets.iter().position(|&stones| stones < 0) {
+                return Err(InvalidBoardError::NegativeStones(
+                    (pocket, side),
+                    pockets[pocket],
+                ));
+            }
+        }
+        let mut board = Board {
+            player_pockets: [0; MAX_POCKETS + 1],
+            opponent_pockets: [0; MAX_POCKETS + 1],
             player_turn,
+            pockets: player_pockets.len() - 1,
+            zobrist_key: 0,
+        };
+        board.player_pockets[..player_pockets.len()].copy_from_slice(player_pockets);
+        board.opponent_pockets[..opponent_pockets.len()].copy_from_slice(opponent_pockets);
+        board.zobrist_key = board.compute_zobrist_key();
+        Ok(board)
+    }
+
+    /// Create a starting board with `pockets` side pockets per side each holding `stones` stones,
+    /// with the Player to move
+    pub fn with_size(pockets: usize, stones: i32) -> Result<Self, InvalidBoardError> {
+        let mut side = vec![stones; pockets];
+        side.push(0);
+        Board::new(&side, &side, PlayerSide::Player, stones)
+    }
+
+    /// Check that the board holds the stones of a game that started with `stones` stones in each
+    /// side pocket. A store holding more than that is reported before the total, since it is the
+    /// more likely typo.
+    pub fn check_total(&self, stones: i32) -> Result<(), InvalidBoardError> {
+        let expected = 2 * self.pockets as i32 * stones;
+        for side in [PlayerSide::Player, PlayerSide::Opponent] {
+            if self.score(side) > expected {
+                return Err(InvalidBoardError::StoreOverflow {
+                    side,
+                    stones: self.score(side),
+                    expected,
+                });
+            }
+        }
+        match self.total_stones() {
+            total if total == expected => Ok(()),
+            total => Err(InvalidBoardError::WrongTotal { expected, total }),
+        }
+    }
+
+    /// The same position with the two sides swapped, so the Player's pockets become the
+    /// Opponent's and the other player is to move
+    pub fn mirrored(&self) -> Self {
+        let mut board = *self;
+        board.player_pockets = self.opponent_pockets;
+        board.opponent_pockets = self.player_pockets;
+        board.player_turn = opposite_player(self.player_turn);
+        board.zobrist_key = board.compute_zobrist_key();
+        board
+    }
+
+    /// The number of stones on the board, including the stores
+    pub fn total_stones(&self) -> i32 {
+        self.player_pockets.iter().sum::<i32>() + self.opponent_pockets.iter().sum::<i32>()
+    }
+
+    /// Number of side pockets on each side (not counting the store)
+    pub fn pockets(&self) -> usize {
+        self.pockets
+    }
+
+    /// Index of the store on each side
+    pub fn store(&self) -> PocketIndex {
+        self.pockets
+    }
+
+    /// The side pockets of one side, not including the store
+    pub fn side_pockets(&self, side: PlayerSide) -> &[i32] {
+        match side {
+            PlayerSide::Player => &self.player_pockets[..self.pockets],
+            PlayerSide::Opponent => &self.opponent_pockets[..self.pockets],
+        }
+    }
+
+    /// The number of stones in a side's store
+    pub fn score(&self, side: PlayerSide) -> i32 {
+        self.get_stones((self.store(), side))
+    }
+
+    /// 64 bit Zobrist key of the board, identifying the stones in each pocket and whose turn it is.
+    /// Stone counts above `MAX_ZOBRIST_STONES` are not distinguished.
+    pub fn zobrist_key(&self) -> u64 {
+        self.zobrist_key
+    }
+
+    /// Compute the Zobrist key from scratch
+    fn compute_zobrist_key(&self) -> u64 {
+        let mut key = match self.player_turn {
+            PlayerSide::Player => 0,
+            PlayerSide::Opponent => opponent_turn_key(),
+        };
+        for pocket in 0..=self.pockets {
+            key ^= pocket_key((pocket, PlayerSide::Player), self.player_pockets[pocket]);
+            key ^= pocket_key(
+                (pocket, PlayerSide::Opponent),
+                self.opponent_pockets[pocket],
+            );
         }
+        key
     }
 
     fn switch_player(&mut self) {
-        self.player_turn = opposite_player(self.player_turn)
+        self.player_turn = opposite_player(self.player_turn);
+        self.zobrist_key ^= opponent_turn_key();
     }
 
     pub fn get_stones(self, pocket: PocketLocation) -> i32 {
@@ -83,39 +247,112 @@ impl Board {
             PlayerSide::Player => self.player_pockets[pocket.0] = 0,
             PlayerSide::Opponent => self.opponent_pockets[pocket.0] = 0,
         }
+        self.zobrist_key ^= pocket_key(pocket, stones);
         stones
     }
 
     fn increment_stones(&mut self, pocket: PocketLocation) {
+        self.add_stones(pocket, 1);
+    }
+
+    fn add_stones(&mut self, pocket: PocketLocation, added: i32) {
+        let stones = self.get_stones(pocket);
         match pocket.1 {
-            PlayerSide::Player => self.player_pockets[pocket.0] += 1,
-            PlayerSide::Opponent => self.opponent_pockets[pocket.0] += 1,
+            PlayerSide::Player => self.player_pockets[pocket.0] += added,
+            PlayerSide::Opponent => self.opponent_pockets[pocket.0] += added,
+        }
+        self.zobrist_key ^= pocket_key(pocket, stones) ^ pocket_key(pocket, stones + added);
+    }
+
+    /// Move the stones left in each side's pockets into that side's store
+    fn sweep(&mut self, trace: &mut Trace) {
+        for side in [PlayerSide::Player, PlayerSide::Opponent] {
+            for pocket in 0..self.pockets {
+                let stones = self.pop_stones((pocket, side));
+                self.add_stones((self.store(), side), stones);
+            }
+        }
+        self.trace(trace, SowingEvent::Sweep);
+    }
+
+    /// Add an event to the trace along with the board after it, if a trace is being kept
+    fn trace(&self, trace: &mut Trace, event: SowingEvent) {
+        if let Some(trace) = trace {
+            trace.push(TraceStep {
+                event,
+                board: *self,
+            });
         }
     }
 
-    fn pickup_stones(&mut self, pocket: PocketLocation) -> PocketLocation {
+    /// The pocket after the given one going counter-clockwise, skipping the stores that the sowing rule skips
+    fn next_pocket(&self, pocket: PocketLocation, sowing: Sowing) -> PocketLocation {
+        let (current_pocket, side) = pocket;
+        let last_pocket = self.pockets - 1;
+        // only the player whose turn it is drops stones in their own store, and only if stores are sown at all
+        if current_pocket == last_pocket && side == self.player_turn && sowing == Sowing::Store {
+            (self.store(), side)
+        } else if current_pocket >= last_pocket {
+            (0, opposite_player(side))
+        } else {
+            (current_pocket + 1, side)
+        }
+    }
+
+    /// Pick up all the stones in a pocket and drop them one by one going counter-clockwise.
+    /// `pickup` is the event traced for picking the stones up.
+    /// Returns the pocket the last stone was dropped in.
+    fn pickup_stones(
+        &mut self,
+        pocket: PocketLocation,
+        sowing: Sowing,
+        pickup: fn(PocketLocation, i32) -> SowingEvent,
+        trace: &mut Trace,
+    ) -> PocketLocation {
+        #[cfg(debug_assertions)]
+        let total = self.total_stones();
         let mut stones = self.pop_stones(pocket);
-        let mut current_pocket = pocket.0;
-        let mut side = pocket.1;
+        self.trace(trace, pickup(pocket, stones));
+        let mut current_pocket = pocket;
         while stones > 0 {
-            current_pocket += 1;
-            // if the player is dropping on their own side, increment their store when the end of the side (index 5) is reached, otherwise, skip the store
-            if side == self.player_turn && current_pocket == 6 {
-                self.increment_stones((6, side));
-                stones -= 1;
-                if stones == 0 {
-                    break;
-                }
-                current_pocket = 0;
-                side = opposite_player(side);
-            } else if current_pocket == 7 || (side != self.player_turn && current_pocket == 6) {
-                current_pocket = 0;
-                side = opposite_player(side);
+            current_pocket = self.next_pocket(current_pocket, sowing);
+            // when stores are skipped, the emptied pocket is also skipped on every lap around the board
+            if sowing == Sowing::SkipStores && current_pocket == pocket {
+                current_pocket = self.next_pocket(current_pocket, sowing);
             }
+            self.increment_stones(current_pocket);
             stones -= 1;
-            self.increment_stones((current_pocket, side));
+            if trace.is_some() {
+                let event = match current_pocket.0 == self.store() {
+                    true => SowingEvent::Store(current_pocket, stones),
+                    false => SowingEvent::Drop(current_pocket, stones),
+                };
+                self.trace(trace, event);
+            }
+        }
+        #[cfg(debug_assertions)]
+        self.check_invariants(total);
+        current_pocket
+    }
+
+    /// Panics if sowing created or lost stones, left a pocket negative, or put stones past the stores
+    #[cfg(debug_assertions)]
+    fn check_invariants(&self, total: i32) {
+        assert_eq!(
+            self.total_stones(),
+            total,
+            "sowing changed the number of stones"
+        );
+        for side in [&self.player_pockets, &self.opponent_pockets] {
+            assert!(
+                side.iter().all(|&stones| stones >= 0),
+                "a pocket went negative"
+            );
+            assert!(
+                side[self.pockets + 1..].iter().all(|&stones| stones == 0),
+                "stones were sown past the store"
+            );
         }
-        (current_pocket, side)
     }
 }
 
@@ -123,6 +360,8 @@ impl Board {
 pub struct Game {
     pub board: Board,
     pub game_state: GameState,
+    /// The rules the game is played with
+    pub rules: Rules,
 }
 
 impl Debug for Game {
@@ -138,67 +377,221 @@ impl Debug for Game {
 }
 
 impl Game {
+    /// Create a game played with the default (Game Pigeon avalanche) rules
     pub fn new(board: Board) -> Self {
-        Game {
+        Game::with_rules(board, Rules::default())
+    }
+
+    /// A game played from `board`. Finished positions, such as one side being empty or a lead
+    /// that can't be caught up, give a game that is already over, the same as `play_move` would
+    /// leave it (with the stones swept if the rules sweep them).
+    pub fn with_rules(board: Board, rules: Rules) -> Self {
+        let mut game = Game {
             board,
             game_state: GameState::InProgress,
-        }
+            rules,
+        };
+        game.update_game_state(&mut None);
+        game
     }
+
     pub fn play_move(&mut self, pocket: PocketLocation) -> Result<(), InvalidPocketError> {
+        self.play_move_with_trace(pocket, &mut None)
+    }
+
+    /// The same game with the two sides swapped (see `Board::mirrored`), including who won
+    pub fn mirrored(&self) -> Self {
+        let game_state = match self.game_state {
+            GameState::InProgress => GameState::InProgress,
+            GameState::Over(GameOver::Win(Winner::Player)) => {
+                GameState::Over(GameOver::Win(Winner::Opponent))
+            }
+            GameState::Over(GameOver::Win(Winner::Opponent)) => {
+                GameState::Over(GameOver::Win(Winner::Player))
+            }
+            GameState::Over(GameOver::Win(Winner::Tie)) => {
+                GameState::Over(GameOver::Win(Winner::Tie))
+            }
+            GameState::Over(GameOver::TechnicalWin(side)) => {
+                GameState::Over(GameOver::TechnicalWin(opposite_player(side)))
+            }
+        };
+        Game {
+            board: self.board.mirrored(),
+            game_state,
+            rules: self.rules,
+        }
+    }
+
+    /// The Player's share of a finished game: 1 for a win, 0.5 for a tie and 0 for a loss.
+    /// None while the game is in progress.
+    pub fn player_result(&self) -> Option<f32> {
+        match self.game_state {
+            GameState::InProgress => None,
+            GameState::Over(GameOver::Win(Winner::Player))
+            | GameState::Over(GameOver::TechnicalWin(PlayerSide::Player)) => Some(1.0),
+            GameState::Over(GameOver::Win(Winner::Opponent))
+            | GameState::Over(GameOver::TechnicalWin(PlayerSide::Opponent)) => Some(0.0),
+            GameState::Over(GameOver::Win(Winner::Tie)) => Some(0.5),
+        }
+    }
+
+    /// Play a move like `play_move`, also returning every step of the move in the order it
+    /// happened, each with the board right after it
+    pub fn play_move_traced(
+        &mut self,
+        pocket: PocketLocation,
+    ) -> Result<Vec<TraceStep>, InvalidPocketError> {
+        let mut steps = Vec::new();
+        self.play_move_with_trace(pocket, &mut Some(&mut steps))?;
+        Ok(steps)
+    }
+
+    fn play_move_with_trace(
+        &mut self,
+        pocket: PocketLocation,
+        trace: &mut Trace,
+    ) -> Result<(), InvalidPocketError> {
         /*
         A move consists of picking up the stones in a pocket and dropping them into the next pockets.
         When the last stone is dropped, 3 things can happen
-        1. If the last stone is dropped in the player's store, the player gets another turn
-        2. If the last stone is dropped in a non-empty pocket, on either side, the player picks up the stones in that pocket and drops them again
-        3. If the last stone is dropped in an empty pocket, the player's turn ends.
+        1. If the last stone is dropped in the player's store, the player gets another turn (if the rules give free turns)
+        2. If the last stone is dropped in a non-empty pocket, on either side, the player picks up the stones in that pocket and drops them again (if the rules use avalanches)
+        3. Otherwise, the rules' capture is applied and the player's turn ends.
         */
         if pocket.1 != self.board.player_turn {
             return Err(InvalidPocketError::WrongPlayer);
         }
+        if pocket.0 == self.board.store() {
+            return Err(InvalidPocketError::StorePocket);
+        }
         // usize can't go below 0 so only check the positive side
-        if pocket.0 > 5 {
+        if pocket.0 > self.board.store() {
             return Err(InvalidPocketError::OutOfBoundsPocket);
         }
         if self.board.get_stones(pocket) == 0 {
             return Err(InvalidPocketError::EmptyPocket);
         }
-        if pocket.0 == 6 {
-            return Err(InvalidPocketError::StorePocket);
-        }
-        let (mut current_pocket, mut side) = self.board.pickup_stones(pocket);
+        let mut last_pocket =
+            self.board
+                .pickup_stones(pocket, self.rules.sowing, SowingEvent::Pickup, trace);
         loop {
-            if side == self.board.player_turn && current_pocket == 6 {
+            if last_pocket == (self.board.store(), self.board.player_turn) {
+                if !self.rules.free_turn {
+                    self.board.switch_player();
+                    self.board
+                        .trace(trace, SowingEvent::TurnSwitch(self.board.player_turn));
+                }
                 break;
             }
-            if self.board.get_stones((current_pocket, side)) == 1 {
-                self.board.switch_player();
-                break;
+            if self.rules.avalanche && self.board.get_stones(last_pocket) > 1 {
+                last_pocket = self.board.pickup_stones(
+                    last_pocket,
+                    self.rules.sowing,
+                    SowingEvent::Avalanche,
+                    trace,
+                );
+                continue;
             }
-            (current_pocket, side) = self.board.pickup_stones((current_pocket, side));
+            self.capture(last_pocket, trace);
+            self.board.switch_player();
+            self.board
+                .trace(trace, SowingEvent::TurnSwitch(self.board.player_turn));
+            break;
         }
-        self.game_state = match self.check_for_game_end() {
+        debug_assert_eq!(self.board.zobrist_key, self.board.compute_zobrist_key());
+        self.update_game_state(trace);
+        Ok(())
+    }
+
+    /// Sets the game state from the board: over if the rules end the game or a lead can no longer
+    /// be caught up, in progress otherwise
+    fn update_game_state(&mut self, trace: &mut Trace) {
+        self.game_state = match self.check_for_game_end(trace) {
             Some(winner) => GameState::Over(GameOver::Win(winner)),
             None => match self.check_for_technical_win() {
                 Some(winner) => GameState::Over(GameOver::TechnicalWin(winner)),
                 None => GameState::InProgress,
             },
         };
-        Ok(())
     }
 
-    fn check_for_game_end(&self) -> Option<Winner> {
-        // if both sides still have some stones, the game is not over
-        if self.board.player_pockets[0..6]
+    /// Apply the rules' capture for a move whose last stone was dropped in the given pocket
+    fn capture(&mut self, last_pocket: PocketLocation, trace: &mut Trace) {
+        let mover = self.board.player_turn;
+        let store = (self.board.store(), mover);
+        match self.rules.capture {
+            Capture::None => {}
+            Capture::EmptyPocket => {
+                let opposite_pocket = (
+                    self.board.pockets() - 1 - last_pocket.0,
+                    opposite_player(mover),
+                );
+                if last_pocket.1 == mover
+                    && self.board.get_stones(last_pocket) == 1
+                    && self.board.get_stones(opposite_pocket) > 0
+                {
+                    for pocket in [last_pocket, opposite_pocket] {
+                        let captured = self.board.pop_stones(pocket);
+                        self.board.add_stones(store, captured);
+                        self.board
+                            .trace(trace, SowingEvent::Capture(pocket, captured));
+                    }
+                }
+            }
+            Capture::TwoOrThree => {
+                if last_pocket.1 == mover {
+                    return;
+                }
+                // capture backwards along the opponent's side while the pockets hold 2 or 3
+                for pocket in (0..=last_pocket.0).rev() {
+                    let stones = self.board.get_stones((pocket, last_pocket.1));
+                    if stones != 2 && stones != 3 {
+                        break;
+                    }
+                    let captured = self.board.pop_stones((pocket, last_pocket.1));
+                    self.board.add_stones(store, captured);
+                    self.board.trace(
+                        trace,
+                        SowingEvent::Capture((pocket, last_pocket.1), captured),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Checks if the game is over, applying the rules' sweep to the board if it is
+    fn check_for_game_end(&mut self, trace: &mut Trace) -> Option<Winner> {
+        let player_empty = self
+            .board
+            .side_pockets(PlayerSide::Player)
             .iter()
-            .any(|&pocket| pocket != 0)
-            && self.board.opponent_pockets[0..6]
-                .iter()
-                .any(|&pocket| pocket != 0)
-        {
+            .all(|&pocket| pocket == 0);
+        let opponent_empty = self
+            .board
+            .side_pockets(PlayerSide::Opponent)
+            .iter()
+            .all(|&pocket| pocket == 0);
+        let over = match self.rules.game_end {
+            // if both sides still have some stones, the game is not over
+            GameEnd::EitherSideEmpty => player_empty || opponent_empty,
+            GameEnd::MoverSideEmpty => match self.board.player_turn {
+                PlayerSide::Player => player_empty,
+                PlayerSide::Opponent => opponent_empty,
+            },
+        };
+        if !over {
             return None;
         }
+        if self.rules.sweep == Sweep::ToOwner {
+            self.board.sweep(trace);
+        }
         // The winner is the player with the most stones in their store
-        match self.board.player_pockets[6].cmp(&self.board.opponent_pockets[6]) {
+        match self
+            .board
+            .score(PlayerSide::Player)
+            .cmp(&self.board.score(PlayerSide::Opponent))
+        {
             Ordering::Greater => Some(Winner::Player),
             Ordering::Less => Some(Winner::Opponent),
             Ordering::Equal => Some(Winner::Tie),
@@ -207,11 +600,21 @@ impl Game {
 
     fn check_for_technical_win(&self) -> Option<PlayerSide> {
         // Checks if there are not enough stones left to change the current leader
+        // Every remaining stone ends up in at most one store whether or not the rules sweep them at
+        // the end of the game, so a lead larger than the remaining stones can't be lost either way
         // get the remaining stones in the pockets (not including the stores)
-        let remaining_stones: i32 = self.board.player_pockets[0..6].iter().sum::<i32>()
-            + self.board.opponent_pockets[0..6].iter().sum::<i32>();
-        let player_score = self.board.player_pockets[6];
-        let opponent_score = self.board.opponent_pockets[6];
+        let remaining_stones: i32 = self
+            .board
+            .side_pockets(PlayerSide::Player)
+            .iter()
+            .sum::<i32>()
+            + self
+                .board
+                .side_pockets(PlayerSide::Opponent)
+                .iter()
+                .sum::<i32>();
+        let player_score = self.board.score(PlayerSide::Player);
+        let opponent_score = self.board.score(PlayerSide::Opponent);
         // if the remaining stones plus the player's score is less than the opponent's score, the opponent wins
         if remaining_stones + player_score < opponent_score {
             return Some(PlayerSide::Opponent);
@@ -241,7 +644,7 @@ pub enum Winner {
     Tie,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InvalidPocketError {
     // Error type for use in board.move
     EmptyPocket,       // pocket is empty
@@ -250,6 +653,191 @@ pub enum InvalidPocketError {
     OutOfBoundsPocket, // pocket is out of bounds
 }
 
+impl Display for InvalidPocketError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidPocketError::EmptyPocket => write!(f, "the pocket is empty"),
+            InvalidPocketError::WrongPlayer => {
+                write!(f, "the pocket belongs to the player who isn't moving")
+            }
+            InvalidPocketError::StorePocket => write!(f, "stores can't be played"),
+            InvalidPocketError::OutOfBoundsPocket => write!(f, "the pocket is not on the board"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidPocketError {}
+
+/// Error type for building a board that can't be played on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidBoardError {
+    /// The sides have a different number of side pockets (Player's, then Opponent's)
+    MismatchedSides(usize, usize),
+    /// A side has no side pockets or more than `MAX_POCKETS`
+    InvalidPocketCount(usize),
+    /// A pocket holds fewer than 0 stones
+    NegativeStones(PocketLocation, i32),
+    /// A store holds more stones than the whole board started with
+    StoreOverflow {
+        side: PlayerSide,
+        stones: i32,
+        expected: i32,
+    },
+    /// The board doesn't hold the stones the game started with
+    WrongTotal { expected: i32, total: i32 },
+}
+
+impl Display for InvalidBoardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidBoardError::MismatchedSides(player, opponent) => write!(
+                f,
+                "the player has {} pockets but the opponent has {}",
+                player, opponent
+            ),
+            InvalidBoardError::InvalidPocketCount(pockets) => write!(
+                f,
+                "a side must have between 1 and {} pockets, not {}",
+                MAX_POCKETS, pockets
+            ),
+            InvalidBoardError::NegativeStones((pocket, side), stones) => {
+                write!(
+                    f,
+                    "{}'s pocket {} can't hold {} stones",
+                    side, pocket, stones
+                )
+            }
+            InvalidBoardError::StoreOverflow {
+                side,
+                stones,
+                expected,
+            } => write!(
+                f,
+                "{}'s store holds {} stones but the game started with {}",
+                side, stones, expected
+            ),
+            InvalidBoardError::WrongTotal { expected, total } => write!(
+                f,
+                "the board holds {} stones but the game started with {}",
+                total, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InvalidBoardError {}
+
+/// Something that happened while a move was played
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SowingEvent {
+    /// The stones in the played pocket were picked up
+    Pickup(PocketLocation, i32),
+    /// A stone was dropped in a side pocket, with the given number of stones left to drop
+    Drop(PocketLocation, i32),
+    /// A stone was dropped in the mover's store, with the given number of stones left to drop
+    Store(PocketLocation, i32),
+    /// The last stone landed in a pocket that already held stones, and all the stones in it
+    /// (including the last one) were picked up again
+    Avalanche(PocketLocation, i32),
+    /// Stones were captured from a pocket into the mover's store
+    Capture(PocketLocation, i32),
+    /// The game ended and each side's remaining stones were moved into that side's store
+    Sweep,
+    /// The move ended and it became the given player's turn
+    TurnSwitch(PlayerSide),
+}
+
+impl SowingEvent {
+    /// The pocket the event happened at, if any
+    pub fn pocket(&self) -> Option<PocketLocation> {
+        match *self {
+            SowingEvent::Pickup(pocket, _)
+            | SowingEvent::Drop(pocket, _)
+            | SowingEvent::Store(pocket, _)
+            | SowingEvent::Avalanche(pocket, _)
+            | SowingEvent::Capture(pocket, _) => Some(pocket),
+            SowingEvent::Sweep | SowingEvent::TurnSwitch(_) => None,
+        }
+    }
+
+    /// The stones still to be dropped after the event, if the mover is holding any
+    pub fn stones_in_hand(&self) -> Option<i32> {
+        match *self {
+            SowingEvent::Pickup(_, stones)
+            | SowingEvent::Drop(_, stones)
+            | SowingEvent::Store(_, stones)
+            | SowingEvent::Avalanche(_, stones) => Some(stones),
+            SowingEvent::Capture(..) | SowingEvent::Sweep | SowingEvent::TurnSwitch(_) => None,
+        }
+    }
+}
+
+impl Display for SowingEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            SowingEvent::Pickup(pocket, stones) => {
+                write!(
+                    f,
+                    "Picked up {} stones from {}",
+                    stones,
+                    pocket_notation(pocket)
+                )
+            }
+            SowingEvent::Drop(pocket, left) => write!(
+                f,
+                "Dropped a stone in {}, {} left",
+                pocket_notation(pocket),
+                left
+            ),
+            SowingEvent::Store(pocket, left) => write!(
+                f,
+                "Dropped a stone in the store {}, {} left",
+                pocket_notation(pocket),
+                left
+            ),
+            SowingEvent::Avalanche(pocket, stones) => write!(
+                f,
+                "Landed in {} and picked up its {} stones",
+                pocket_notation(pocket),
+                stones
+            ),
+            SowingEvent::Capture(pocket, stones) => {
+                write!(
+                    f,
+                    "Captured {} stones from {}",
+                    stones,
+                    pocket_notation(pocket)
+                )
+            }
+            SowingEvent::Sweep => write!(f, "Moved the stones left on each side into the stores"),
+            SowingEvent::TurnSwitch(side) => write!(f, "{}'s turn", side),
+        }
+    }
+}
+
+/// A step of a traced move: an event and the board right after it
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TraceStep {
+    pub event: SowingEvent,
+    pub board: Board,
+}
+
+/// Where the steps of a move are collected when it is being traced
+type Trace<'a> = Option<&'a mut Vec<TraceStep>>;
+
+impl Debug for TraceStep {
+    /// Shows the board with the event's pocket marked and the stones left to drop
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let debug_game = DebugGame {
+            board: self.board,
+            game_state: None,
+            selected_pocket_location: self.event.pocket(),
+            stones: self.event.stones_in_hand(),
+        };
+        write!(f, "{}\n{:?}", self.event, debug_game)
+    }
+}
+
 struct DebugGame {
     board: Board,
     selected_pocket_location: Option<PocketLocation>,
@@ -270,17 +858,19 @@ impl Debug for DebugGame {
             }
             "  ".to_string()
         }
+        let pockets = self.board.pockets();
+        let store = self.board.store();
         let opponent_store_str = format!(
             "    {: >4}  {}",
-            mark((6, PlayerSide::Opponent), self.selected_pocket_location),
-            self.board.opponent_pockets[6]
+            mark((store, PlayerSide::Opponent), self.selected_pocket_location),
+            self.board.score(PlayerSide::Opponent)
         );
         let player_store_str = format!(
             "    {: >4}  {}",
-            mark((6, PlayerSide::Player), self.selected_pocket_location),
-            self.board.player_pockets[6]
+            mark((store, PlayerSide::Player), self.selected_pocket_location),
+            self.board.score(PlayerSide::Player)
         );
-        let opponent_pockets_str = (0..6)
+        let opponent_pockets_str = (0..pockets)
             .rev()
             .map(|i| {
                 format!(
@@ -290,7 +880,7 @@ impl Debug for DebugGame {
                 )
             })
             .collect::<Vec<String>>();
-        let player_pockets_str = (0..6)
+        let player_pockets_str = (0..pockets)
             .map(|i| {
                 format!(
                     "{: >4}  {}",
@@ -299,7 +889,7 @@ impl Debug for DebugGame {
                 )
             })
             .collect::<Vec<String>>();
-        let pocket_lines = (0..6)
+        let pocket_lines = (0..pockets)
             .map(|i| format!("{}  {}", player_pockets_str[i], opponent_pockets_str[i]))
             .collect::<Vec<String>>()
             .join("\n");
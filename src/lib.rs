@@ -0,0 +1,19 @@
+pub mod error;
+pub mod eval;
+pub mod game;
+pub mod mcts;
+pub mod minimax;
+pub mod player;
+pub mod position;
+#[cfg(feature = "qlearning")]
+pub mod qlearning_move;
+pub mod record;
+mod rng;
+pub mod rules;
+pub mod solver;
+#[cfg(test)]
+mod test;
+pub mod tournament;
+pub mod transposition;
+pub mod tuning;
+pub mod zobrist;
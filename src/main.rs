@@ -1,17 +1,109 @@
 use std::io::Write;
+use std::time::Duration;
 
-use crate::game::{Game, PlayerSide};
-use crate::solver::{EvalMethod, SequenceTree};
+use mancala_rs::error::Error;
+use mancala_rs::eval::FeatureWeights;
+use mancala_rs::game::{
+    Board, Game, GameState, PlayerSide, PocketIndex, DEFAULT_POCKETS, DEFAULT_STONES, MAX_POCKETS,
+};
+use mancala_rs::mcts::{MctsConfig, MctsTree, Playout};
+use mancala_rs::minimax::GameTree;
+use mancala_rs::player::{
+    GreedyPlayer, HumanPlayer, MctsPlayer, MinimaxPlayer, Player, RandomPlayer,
+};
+#[cfg(feature = "qlearning")]
+use mancala_rs::qlearning_move::{QLearningConfig, QPolicy, QTableHeader};
+use mancala_rs::record::{pocket_notation, GameRecord};
+use mancala_rs::rules::{Sweep, Variant};
+use mancala_rs::solver::SequenceTree;
+use mancala_rs::tournament::{play_match, MatchConfig};
+use mancala_rs::transposition::TranspositionTable;
+use mancala_rs::tuning::{tune, TuningConfig};
 
-pub mod game;
-pub mod minimax;
-pub mod qlearning_move;
-pub mod solver;
-pub mod test;
+/// Print the prompt and read a line of input, returning None once stdin is closed
+fn read_input() -> Result<Option<String>, Error> {
+    print!("> ");
+    std::io::stdout().flush()?;
+    let mut input = String::new();
+    if std::io::stdin().read_line(&mut input)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(input))
+}
+
+/// Games played with Pla(y) Game are stopped after this many turns, in case neither side can finish
+const MAX_GAME_TURNS: usize = 500;
+
+/// The players Pla(y) Game can choose from, in the order they are listed
+const PLAYER_CHOICES: [&str; 6] = [
+    "Human",
+    "Greedy, with the current weights",
+    "Minimax searching 2 turns ahead, with the current weights",
+    "Monte Carlo tree search, 2000 iterations",
+    "Random",
+    "Q-learning, with the current policy",
+];
+
+/// The player for a choice from `PLAYER_CHOICES`, numbered from 1
+fn new_player(
+    choice: usize,
+    weights: FeatureWeights,
+    #[cfg(feature = "qlearning")] policy: &QPolicy,
+) -> Option<Box<dyn Player>> {
+    match choice {
+        1 => Some(Box::new(HumanPlayer)),
+        2 => Some(Box::new(GreedyPlayer::with_evaluator(weights))),
+        3 => Some(Box::new(MinimaxPlayer::with_evaluator(2, weights))),
+        4 => Some(Box::new(MctsPlayer::new(MctsConfig::default()))),
+        5 => Some(Box::new(RandomPlayer::new(1))),
+        #[cfg(feature = "qlearning")]
+        6 => Some(Box::new(policy.clone())),
+        _ => None,
+    }
+}
+
+/// Print the numbered list of `PLAYER_CHOICES`
+fn print_player_choices() {
+    for (i, choice) in PLAYER_CHOICES.iter().enumerate() {
+        // the last choice needs the qlearning feature
+        if !cfg!(feature = "qlearning") && i + 1 == PLAYER_CHOICES.len() {
+            continue;
+        }
+        println!("{}: {}", i + 1, choice);
+    }
+}
+
+/// Print the pockets of a turn as they are played, then the game at the end of the turn
+fn show_turn(mut game: Game, sequence: &[PocketIndex]) {
+    for pocket in sequence {
+        print!("{} ", pocket);
+        if let Err(error) = game.play_move((*pocket, game.board.player_turn)) {
+            println!("Couldn't play {}: {}", pocket, error);
+            break;
+        }
+    }
+    println!();
+    println!("{:?}", game);
+}
 
 fn main() {
-    let mut game = Game::default();
-    let mut stash = Game::default();
+    if let Err(error) = run() {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut record = GameRecord::new(Game::default());
+    let mut stash = record.clone();
+    // pockets on each side and stones in each pocket for new games
+    let mut size = (DEFAULT_POCKETS, DEFAULT_STONES);
+    let mut table = TranspositionTable::default();
+    // evaluator used by (F)ind best move
+    let mut weights = FeatureWeights::default();
+    // policy trained by (Q)-learning, kept between trainings
+    #[cfg(feature = "qlearning")]
+    let mut policy = QPolicy::new();
     // get user input
     //println!("{} nodes", tree.nodes.len());
     loop {
@@ -33,100 +125,361 @@ fn main() {
         println!("(S)tash Game");
         println!("(L)oad Game");
         println!("(T)est Move");
+        println!("Tr(a)ce Move");
         println!("(P)lay Move");
         println!("(F)ind best move");
-        let mut input = String::new();
-        print!("> ");
-        std::io::stdout().flush().unwrap();
-        std::io::stdin().read_line(&mut input).unwrap();
+        println!("Top (K) turns");
+        println!("Monte Carlo searc(h)");
+        println!("(C)hange Rules");
+        println!("(B)oard Size");
+        println!("(U)ndo Move");
+        println!("R(e)do Move");
+        println!("(J)ump to Move");
+        println!("Sa(v)e Record");
+        println!("(O)pen Record");
+        println!("(W)eights");
+        println!("Tu(n)e Weights");
+        println!("Pla(y) Game");
+        println!("(X) Match");
+        #[cfg(feature = "qlearning")]
+        println!("(Q)-learning");
+        let game = *record.current();
+        let Some(input) = read_input()? else {
+            return Ok(());
+        };
         let input = input.trim().to_lowercase();
         match input.as_str() {
             "d" => {
                 println!("{:?}", game);
+                println!("Position: {}", game);
             }
             "r" => {
-                game = Game::default();
-                println!("{:?}", game);
+                record = GameRecord::new(Game::with_rules(
+                    Board::with_size(size.0, size.1)?,
+                    game.rules,
+                ));
+                println!("{:?}", record.current());
+            }
+            "b" => {
+                println!(
+                    "Enter the number of pockets on each side (1 to {}) and the stones in each pocket:",
+                    MAX_POCKETS
+                );
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                let fields = input.split_whitespace().collect::<Vec<&str>>();
+                let (pockets, stones) = match fields.as_slice() {
+                    [pockets, stones] => match (pockets.parse::<usize>(), stones.parse::<i32>()) {
+                        (Ok(pockets), Ok(stones)) => (pockets, stones),
+                        _ => {
+                            println!("Invalid input");
+                            continue;
+                        }
+                    },
+                    _ => {
+                        println!("Invalid input");
+                        continue;
+                    }
+                };
+                match Board::with_size(pockets, stones) {
+                    Ok(board) => {
+                        size = (pockets, stones);
+                        record = GameRecord::new(Game::with_rules(board, game.rules));
+                        println!("{:?}", record.current());
+                    }
+                    Err(error) => println!("Invalid board: {}", error),
+                }
             }
             "m" => {
-                println!("Enter the player side pockets:");
-                let mut input = String::new();
-                print!("> ");
-                std::io::stdout().flush().unwrap();
-                std::io::stdin().read_line(&mut input).unwrap();
-                let input = input.trim().to_lowercase();
-                let player_pockets: [i32; 7] = input
-                    .split_whitespace()
-                    .map(|x| x.parse::<i32>().unwrap())
-                    .collect::<Vec<i32>>()
-                    .try_into()
-                    .unwrap();
-                println!("Enter the opponent side pockets:");
-                let mut input = String::new();
-                print!("> ");
-                std::io::stdout().flush().unwrap();
-                std::io::stdin().read_line(&mut input).unwrap();
-                let input = input.trim().to_lowercase();
-                let opponent_pockets: [i32; 7] = input
-                    .split_whitespace()
-                    .map(|x| x.parse::<i32>().unwrap())
-                    .collect::<Vec<i32>>()
-                    .try_into()
-                    .unwrap();
-                println!("Select current player turn: \n1: Player\n2: Opponent");
-                let mut input = String::new();
-                print!("> ");
-                std::io::stdout().flush().unwrap();
-                std::io::stdin().read_line(&mut input).unwrap();
-                // let trimmed_input = input.trim().to_lowercase().as_str();
-                let player_turn: PlayerSide = match input.trim().to_lowercase().as_str() {
-                    "1" => PlayerSide::Player,
-                    "2" => PlayerSide::Opponent,
-                    &_ => PlayerSide::Player,
-                };
-                game = Game::new(game::Board {
-                    player_pockets,
-                    opponent_pockets,
-                    player_turn,
-                });
+                println!("Enter the position, e.g. 5,3,2,0,1,7/12 7,2,4,9,0,2/9 P:");
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                let board = match input.parse::<Board>() {
+                    Ok(board) => board,
+                    Err(error) => {
+                        println!("Invalid position: {}", error);
+                        continue;
+                    }
+                };
+                // positions that can't come from the current starting board are usually typos,
+                // but puzzles can be entered as free-form positions
+                if let Err(error) = board.check_total(size.1) {
+                    println!("{}", error);
+                    println!("Use it anyway as a free-form position? (y/n)");
+                    let Some(input) = read_input()? else {
+                        return Ok(());
+                    };
+                    if input.trim().to_lowercase() != "y" {
+                        continue;
+                    }
+                }
+                record = GameRecord::new(Game::with_rules(board, game.rules));
+                println!("{:?}", record.current());
+            }
+            "c" => {
+                println!("Select the rules to play with:");
+                for (i, variant) in Variant::ALL.iter().enumerate() {
+                    println!("{}: {}", i + 1, variant);
+                }
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                let variant = match input.trim().parse::<usize>() {
+                    Ok(n) if (1..=Variant::ALL.len()).contains(&n) => Variant::ALL[n - 1],
+                    _ => {
+                        println!("Invalid input");
+                        continue;
+                    }
+                };
+                let mut rules = variant.rules();
+                println!(
+                    "Move the stones left on each side into that side's store when the game ends? (y/n, default {})",
+                    match rules.sweep {
+                        Sweep::ToOwner => "y",
+                        Sweep::None => "n",
+                    }
+                );
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                match input.trim().to_lowercase().as_str() {
+                    "" => {}
+                    "y" => rules.sweep = Sweep::ToOwner,
+                    "n" => rules.sweep = Sweep::None,
+                    _ => {
+                        println!("Invalid input");
+                        continue;
+                    }
+                }
+                record = GameRecord::new(Game::with_rules(game.board, rules));
+                println!("Playing with {} rules", variant);
             }
             "s" => {
-                stash = game;
+                stash = record.clone();
                 println!("Stashed game");
             }
             "l" => {
-                game = stash;
+                record = stash.clone();
                 println!("Loaded game");
             }
+            "u" => {
+                if record.undo() {
+                    println!("{:?}", record.current());
+                } else {
+                    println!("No moves to undo");
+                }
+            }
+            "e" => {
+                if record.redo() {
+                    println!("{:?}", record.current());
+                } else {
+                    println!("No moves to redo");
+                }
+            }
+            "j" => {
+                println!("0: Start of the game");
+                for (i, entry) in record.entries().iter().enumerate() {
+                    let marker = if i + 1 == record.ply() { " <-" } else { "" };
+                    println!("{}: {}{}", i + 1, pocket_notation(entry.pocket), marker);
+                }
+                println!("Enter the move to jump to:");
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                match input.trim().parse::<usize>() {
+                    Ok(ply) if record.jump_to(ply) => println!("{:?}", record.current()),
+                    _ => {
+                        println!("Invalid input");
+                    }
+                }
+            }
+            "v" => {
+                println!("Enter the file to save the record to:");
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                match std::fs::write(input.trim(), record.to_string()) {
+                    Ok(_) => println!("Saved record to {}", input.trim()),
+                    Err(error) => println!("Couldn't save record: {}", error),
+                }
+            }
+            "o" => {
+                println!("Enter the file to open the record from:");
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                let text = match std::fs::read_to_string(input.trim()) {
+                    Ok(text) => text,
+                    Err(error) => {
+                        println!("Couldn't open record: {}", error);
+                        continue;
+                    }
+                };
+                match text.parse::<GameRecord>() {
+                    Ok(opened) => {
+                        record = opened;
+                        println!("{:?}", record.current());
+                    }
+                    Err(error) => println!("Couldn't read record: {}", error),
+                }
+            }
+            "k" => {
+                println!("Enter the number of turns to list (default 5):");
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                let k = match input.trim() {
+                    "" => 5,
+                    input => match input.parse::<usize>() {
+                        Ok(k) if k > 0 => k,
+                        _ => {
+                            println!("Invalid input");
+                            continue;
+                        }
+                    },
+                };
+                let mut tree = SequenceTree::new(game);
+                tree.generate_tree_parallel(game.board.player_turn);
+                match tree.get_best_sequences(&weights, k, true, &game.board.player_turn) {
+                    Ok(ranked) => {
+                        for (i, turn) in ranked.iter().enumerate() {
+                            let sequence = turn
+                                .sequence
+                                .iter()
+                                .map(|pocket| pocket.to_string())
+                                .collect::<Vec<String>>()
+                                .join(" ");
+                            println!("{}: {} (evaluation {})", i + 1, sequence, turn.evaluation);
+                            println!("   Position: {}", turn.game.board);
+                        }
+                    }
+                    Err(error) => println!("{}", error),
+                }
+            }
+            "w" => {
+                println!("{}", weights);
+                println!("Enter the weights file to load (leave empty to keep the weights above):");
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                if input.trim().is_empty() {
+                    continue;
+                }
+                let text = match std::fs::read_to_string(input.trim()) {
+                    Ok(text) => text,
+                    Err(error) => {
+                        println!("Couldn't open weights: {}", error);
+                        continue;
+                    }
+                };
+                match text.parse::<FeatureWeights>() {
+                    Ok(loaded) => {
+                        weights = loaded;
+                        // cached values were found with the old weights
+                        table = TranspositionTable::default();
+                        print!("{}", weights);
+                    }
+                    Err(error) => println!("Couldn't read weights: {}", error),
+                }
+            }
+            "n" => {
+                let mut config = TuningConfig::default();
+                println!(
+                    "Enter the number of self-play games (default {}):",
+                    config.games
+                );
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                match input.trim() {
+                    "" => {}
+                    input => match input.parse::<usize>() {
+                        Ok(games) if games > 0 => config.games = games,
+                        _ => {
+                            println!("Invalid input");
+                            continue;
+                        }
+                    },
+                }
+                println!("Enter the file to save the weights to:");
+                let Some(path) = read_input()? else {
+                    return Ok(());
+                };
+                println!(
+                    "Playing {} games from the current position, searching {} turns ahead...",
+                    config.games, config.depth
+                );
+                let result = tune(game, weights, &config);
+                println!(
+                    "Fit to {} positions, log loss {}",
+                    result.positions, result.loss
+                );
+                print!("{}", result.weights);
+                match std::fs::write(path.trim(), result.weights.to_string()) {
+                    Ok(_) => println!("Saved weights to {}", path.trim()),
+                    Err(error) => println!("Couldn't save weights: {}", error),
+                }
+            }
             "t" => {
                 let mut test_game = game;
                 println!("Enter the pocket to test:");
-                let mut input = String::new();
-                print!("> ");
-                std::io::stdout().flush().unwrap();
-                std::io::stdin().read_line(&mut input).unwrap();
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
                 let input = input.trim().to_lowercase();
                 match input.parse::<usize>() {
                     Ok(n) => match test_game.play_move((n, test_game.board.player_turn)) {
                         Ok(_) => println!("{:?}", test_game),
-                        Err(_) => println!("Invalid input"),
+                        Err(error) => println!("Invalid move: {}", error),
                     },
                     Err(_) => {
                         println!("Invalid input");
                     }
                 }
             }
+            "a" => {
+                let mut test_game = game;
+                println!("Enter the pocket to trace:");
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                let pocket = match input.trim().parse::<usize>() {
+                    Ok(pocket) => pocket,
+                    Err(_) => {
+                        println!("Invalid input");
+                        continue;
+                    }
+                };
+                println!("Show the board after every step? (y/n, default n)");
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                let show_boards = input.trim().to_lowercase() == "y";
+                match test_game.play_move_traced((pocket, test_game.board.player_turn)) {
+                    Ok(steps) => {
+                        for step in steps {
+                            match show_boards {
+                                true => println!("{:?}", step),
+                                false => println!("{}", step.event),
+                            }
+                        }
+                        println!("{:?}", test_game);
+                    }
+                    Err(error) => println!("Invalid move: {}", error),
+                }
+            }
             "p" => {
                 println!("Enter the pocket to play:");
-                let mut input = String::new();
-                print!("> ");
-                std::io::stdout().flush().unwrap();
-                std::io::stdin().read_line(&mut input).unwrap();
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
                 let input = input.trim().to_lowercase();
                 match input.parse::<usize>() {
-                    Ok(n) => match game.play_move((n, game.board.player_turn)) {
-                        Ok(_) => println!("{:?}", game),
-                        Err(_) => println!("Invalid input"),
+                    Ok(n) => match record.play_move(n) {
+                        Ok(_) => println!("{:?}", record.current()),
+                        Err(error) => println!("Invalid move: {}", error),
                     },
                     Err(_) => {
                         println!("Invalid input");
@@ -134,24 +487,314 @@ fn main() {
                 }
             }
             "f" => {
-                println!("Generating sequence tree...");
-                let mut tree = SequenceTree::new(game);
-                tree.generate_tree(game.board.player_turn, None);
-                let mut test_game = game;
-                println!("Finding best move...");
-                let best_sequence = tree.get_best_sequence(
-                    &EvalMethod::ByDifference,
-                    true,
-                    &test_game.board.player_turn,
+                println!("Enter the number of turns to search (default 2):");
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                let depth = match input.trim() {
+                    "" => 2,
+                    input => match input.parse::<usize>() {
+                        Ok(depth) if depth > 0 => depth,
+                        _ => {
+                            println!("Invalid input");
+                            continue;
+                        }
+                    },
+                };
+                let best_sequence = if depth == 1 {
+                    println!("Generating sequence tree...");
+                    let mut tree = SequenceTree::new(game);
+                    tree.generate_tree_parallel(game.board.player_turn);
+                    let size = tree.size();
+                    println!("Generated {} moves using {} bytes", size.nodes, size.bytes);
+                    println!("Finding best move...");
+                    match tree.get_best_sequence(&weights, true, &game.board.player_turn) {
+                        Ok(sequence) => sequence,
+                        Err(error) => {
+                            println!("{}", error);
+                            continue;
+                        }
+                    }
+                } else {
+                    println!("Searching {} turns ahead...", depth);
+                    let mut tree = GameTree::with_evaluator(game, weights);
+                    let result = match tree.search(depth, &mut table) {
+                        Ok(result) => result,
+                        Err(error) => {
+                            println!("{}", error);
+                            continue;
+                        }
+                    };
+                    println!(
+                        "Generated {} turns, minimax value: {}",
+                        tree.node_count(),
+                        result.value
+                    );
+                    println!(
+                        "Visited {} turns, {} evaluations, {} cutoffs, {} turns pruned",
+                        result.stats.nodes_visited,
+                        result.stats.leaf_evaluations,
+                        result.stats.cutoffs,
+                        result.stats.turns_pruned
+                    );
+                    println!(
+                        "{} table hits, {} table cutoffs, {}/{} table slots used",
+                        result.stats.table_hits,
+                        result.stats.table_cutoffs,
+                        table.used_slots(),
+                        table.capacity()
+                    );
+                    result.sequence
+                };
+                show_turn(game, &best_sequence);
+            }
+            "h" => {
+                let mut config = MctsConfig::default();
+                println!(
+                    "Enter the number of iterations (default {}):",
+                    config.iterations
+                );
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                match input.trim() {
+                    "" => {}
+                    input => match input.parse::<usize>() {
+                        Ok(iterations) if iterations > 0 => config.iterations = iterations,
+                        _ => {
+                            println!("Invalid input");
+                            continue;
+                        }
+                    },
+                }
+                println!("Enter the time limit in seconds (leave empty for no limit):");
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                match input.trim() {
+                    "" => {}
+                    input => match input.parse::<f32>() {
+                        Ok(seconds) if seconds > 0.0 => {
+                            config.time_limit = Some(Duration::from_secs_f32(seconds))
+                        }
+                        _ => {
+                            println!("Invalid input");
+                            continue;
+                        }
+                    },
+                }
+                println!("Play out games with (r)andom or (g)reedy moves? (default g)");
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                match input.trim().to_lowercase().as_str() {
+                    "" | "g" => config.playout = Playout::Greedy,
+                    "r" => config.playout = Playout::Random,
+                    _ => {
+                        println!("Invalid input");
+                        continue;
+                    }
+                }
+                println!("Searching...");
+                let mut tree = MctsTree::new(game, config);
+                let result = match tree.search() {
+                    Ok(result) => result,
+                    Err(error) => {
+                        println!("{}", error);
+                        continue;
+                    }
+                };
+                println!(
+                    "Ran {} iterations in {:?}, generated {} turns",
+                    result.iterations,
+                    result.elapsed,
+                    tree.node_count()
+                );
+                println!(
+                    "Best turn visited {} times, winning {:.1}% of its playouts",
+                    result.visits,
+                    result.win_rate * 100.0
+                );
+                show_turn(game, &result.sequence);
+            }
+            "y" => {
+                let mut players = Vec::new();
+                for side in [PlayerSide::Player, PlayerSide::Opponent] {
+                    println!("Choose who plays the {}:", side);
+                    print_player_choices();
+                    let Some(input) = read_input()? else {
+                        return Ok(());
+                    };
+                    let player = input.trim().parse::<usize>().ok().and_then(|choice| {
+                        new_player(
+                            choice,
+                            weights,
+                            #[cfg(feature = "qlearning")]
+                            &policy,
+                        )
+                    });
+                    match player {
+                        Some(player) => players.push(player),
+                        None => break,
+                    }
+                }
+                let [mut player, mut opponent] = match <[_; 2]>::try_from(players) {
+                    Ok(players) => players,
+                    Err(_) => {
+                        println!("Invalid input");
+                        continue;
+                    }
+                };
+                'game: for _ in 0..MAX_GAME_TURNS {
+                    let game = *record.current();
+                    if game.game_state != GameState::InProgress {
+                        break;
+                    }
+                    let mover = match game.board.player_turn {
+                        PlayerSide::Player => &mut player,
+                        PlayerSide::Opponent => &mut opponent,
+                    };
+                    let sequence = match mover.choose_turn(&game) {
+                        Ok(sequence) => sequence,
+                        Err(error) => {
+                            println!("{} has no turn to play: {}", mover.name(), error);
+                            break;
+                        }
+                    };
+                    print!("{} ({}) plays ", mover.name(), game.board.player_turn);
+                    show_turn(game, &sequence);
+                    for pocket in sequence {
+                        if let Err(error) = record.play_move(pocket) {
+                            println!("Couldn't play {}: {}", pocket, error);
+                            // the rest of the turn can't be played either, so the game stops
+                            break 'game;
+                        }
+                    }
+                }
+            }
+            "x" => {
+                let mut players = Vec::new();
+                for order in ["first", "second"] {
+                    println!("Choose the {} player:", order);
+                    print_player_choices();
+                    let Some(input) = read_input()? else {
+                        return Ok(());
+                    };
+                    let player = input.trim().parse::<usize>().ok().and_then(|choice| {
+                        new_player(
+                            choice,
+                            weights,
+                            #[cfg(feature = "qlearning")]
+                            &policy,
+                        )
+                    });
+                    match player {
+                        Some(player) => players.push(player),
+                        None => break,
+                    }
+                }
+                let [mut first, mut second] = match <[_; 2]>::try_from(players) {
+                    Ok(players) => players,
+                    Err(_) => {
+                        println!("Invalid input");
+                        continue;
+                    }
+                };
+                let mut config = MatchConfig::default();
+                println!("Enter the number of games (default {}):", config.games);
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                match input.trim() {
+                    "" => {}
+                    input => match input.parse::<usize>() {
+                        Ok(games) if games > 0 => config.games = games,
+                        _ => {
+                            println!("Invalid input");
+                            continue;
+                        }
+                    },
+                }
+                println!("Start from balanced openings instead of the current position? (y/n, default n)");
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                match input.trim().to_lowercase().as_str() {
+                    "" | "n" => {}
+                    "y" => config.balanced_openings = true,
+                    _ => {
+                        println!("Invalid input");
+                        continue;
+                    }
+                }
+                println!("Playing {} games...", config.games);
+                let result = play_match(game, first.as_mut(), second.as_mut(), &config);
+                println!("{}", result);
+            }
+            #[cfg(feature = "qlearning")]
+            "q" => {
+                println!("Enter the file to load the policy from (leave empty to keep the current policy):");
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                if !input.trim().is_empty() {
+                    match QPolicy::load(input.trim()) {
+                        Ok(loaded) => policy = loaded,
+                        Err(error) => {
+                            println!("Couldn't load policy: {}", error);
+                            continue;
+                        }
+                    }
+                }
+                let mut config = QLearningConfig::default();
+                println!(
+                    "Enter the number of self-play games to train on (default {}, 0 to only ask the policy):",
+                    config.episodes
                 );
-                for pocket in best_sequence {
-                    print!("{} ", pocket);
-                    test_game
-                        .play_move((pocket, test_game.board.player_turn))
-                        .unwrap();
-                }
-                println!();
-                println!("{:?}", test_game);
+                let Some(input) = read_input()? else {
+                    return Ok(());
+                };
+                match input.trim() {
+                    "" => {}
+                    input => match input.parse::<usize>() {
+                        Ok(episodes) => config.episodes = episodes,
+                        _ => {
+                            println!("Invalid input");
+                            continue;
+                        }
+                    },
+                }
+                if config.episodes > 0 {
+                    if policy
+                        .header()
+                        .is_some_and(|header| *header != QTableHeader::for_game(&game))
+                    {
+                        println!("The policy was trained for different rules or board size, training starts over");
+                    }
+                    println!(
+                        "Training on {} games from the current position...",
+                        config.episodes
+                    );
+                    policy.train(game, &config);
+                    println!("Enter the file to save the policy to (leave empty to not save it):");
+                    let Some(path) = read_input()? else {
+                        return Ok(());
+                    };
+                    if !path.trim().is_empty() {
+                        match policy.save(path.trim()) {
+                            Ok(_) => println!("Saved policy to {}", path.trim()),
+                            Err(error) => println!("Couldn't save policy: {}", error),
+                        }
+                    }
+                }
+                println!("Learned values for {} positions", policy.len());
+                if !policy.knows(&game) {
+                    println!("The current position hasn't been learned, playing the first pocket");
+                }
+                match policy.choose_turn(&game) {
+                    Ok(sequence) => show_turn(game, &sequence),
+                    Err(error) => println!("{}", error),
+                }
             }
             _ => {
                 println!("Invalid input");
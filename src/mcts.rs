@@ -0,0 +1,342 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::game::{Game, GameState, PlayerSide, PocketIndex};
+use crate::rng::Rng;
+use crate::solver::{SequenceNodeEnum, SequenceTree};
+use crate::transposition::TranspositionTable;
+
+/*
+The Monte Carlo tree is stored the same way as the game tree: a vector of nodes referenced by their index.
+Each edge is a whole turn (a leaf of a SequenceTree), and a node's turns are only generated the first
+time the search reaches it.
+struct MctsTree {
+    nodes: Vec<
+        struct MctsNode {
+            game: Game, // The position at the end of the turn (or the root position)
+            parent: Option<usize>, // The index of the parent node, None for the root
+            sequence: Vec<PocketIndex>, // The pockets played during the turn
+            children: Vec<usize>,
+            untried: Option<Vec<(Vec<PocketIndex>, Game)>>, // Turns not added as children yet
+            visits: u32,
+            player_wins: f32, // Sum of the playout results through this node, from the Player's view
+        },
+    >,
+}
+Each iteration selects a path down the tree with UCT, adds one untried turn as a new node, plays the
+game out from there, and adds the result to every node on the path.
+Untried turns are added best first (by the mover's score difference at the end of the turn), and only
+as fast as progressive widening allows.
+*/
+
+pub type MctsIndex = usize;
+
+/// Playouts that are still going after this many moves are scored by the store difference
+const MAX_PLAYOUT_MOVES: usize = 1000;
+
+/// How the game is played out from a new node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Playout {
+    /// Every move is a random legal pocket
+    Random,
+    /// Pockets that end in the mover's store (a free turn) are played first, then pockets that
+    /// score the most, with ties broken at random
+    Greedy,
+}
+
+/// Settings for `MctsTree::search`
+#[derive(Debug, Clone, Copy)]
+pub struct MctsConfig {
+    /// The search stops after this many iterations
+    pub iterations: usize,
+    /// The search also stops once this much time has passed, if set
+    pub time_limit: Option<Duration>,
+    /// The UCT exploration constant; higher values try less visited turns more often
+    pub exploration: f32,
+    /// Progressive widening exponent: a node visited n times has at most (n + 1)^widening children.
+    /// Avalanche positions can have thousands of turns, so new turns are only added as the
+    /// search keeps returning to a node. 1.0 adds every turn before revisiting any of them.
+    pub widening: f32,
+    pub playout: Playout,
+    /// Seed for the playouts; the same seed and iteration budget always give the same turn
+    pub seed: u64,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig {
+            iterations: 2000,
+            time_limit: None,
+            exploration: std::f32::consts::SQRT_2,
+            widening: 0.5,
+            playout: Playout::Greedy,
+            seed: 1,
+        }
+    }
+}
+
+/// The result of a Monte Carlo tree search
+#[derive(Debug, Clone)]
+pub struct MctsResult {
+    /// The pockets to play this turn, in order
+    pub sequence: Vec<PocketIndex>,
+    /// The share of playouts through the chosen turn won by the player to move (ties count half)
+    pub win_rate: f32,
+    /// The number of times the chosen turn was visited
+    pub visits: u32,
+    /// The number of iterations the search ran
+    pub iterations: usize,
+    /// How long the search took
+    pub elapsed: Duration,
+}
+
+struct MctsNode {
+    /// The position at the end of the turn, or the starting position for the root
+    game: Game,
+    /// The index of the parent node in the nodes vector, None for the root
+    parent: Option<MctsIndex>,
+    /// The pockets played during the turn, in order (empty for the root)
+    sequence: Vec<PocketIndex>,
+    /// The indices of the children nodes in the nodes vector
+    children: Vec<MctsIndex>,
+    /// Turns that haven't been added as children yet, None until the node is first expanded
+    untried: Option<Vec<(Vec<PocketIndex>, Game)>>,
+    /// The number of playouts through this node
+    visits: u32,
+    /// The sum of the results of the playouts through this node, from the Player's view
+    player_wins: f32,
+}
+
+impl MctsNode {
+    fn new(game: Game, parent: Option<MctsIndex>, sequence: Vec<PocketIndex>) -> Self {
+        MctsNode {
+            game,
+            parent,
+            sequence,
+            children: Vec::new(),
+            untried: None,
+            visits: 0,
+            player_wins: 0.0,
+        }
+    }
+
+    /// The share of playouts through this node won by `side`
+    fn win_rate(&self, side: PlayerSide) -> f32 {
+        let player_rate = self.player_wins / self.visits.max(1) as f32;
+        match side {
+            PlayerSide::Player => player_rate,
+            PlayerSide::Opponent => 1.0 - player_rate,
+        }
+    }
+}
+
+/// Tree of whole turns searched with Monte Carlo tree search
+pub struct MctsTree {
+    nodes: Vec<MctsNode>,
+    config: MctsConfig,
+    rng: Rng,
+    /// Table used to skip repeated positions while generating the sequence tree of each turn
+    sequence_table: TranspositionTable,
+}
+
+/// Every distinct position the player to move can reach by the end of their turn, with the first
+/// turn found that reaches it
+pub(crate) fn distinct_turns(
+    game: &Game,
+    table: &mut TranspositionTable,
+) -> Vec<(Vec<PocketIndex>, Game)> {
+    let mut sequence_tree = SequenceTree::new(*game);
+    sequence_tree.generate_tree_with_table(game.board.player_turn, table);
+    let mut seen = HashSet::new();
+    let mut turns = Vec::new();
+    for leaf_index in &sequence_tree.leaf_nodes {
+        let SequenceNodeEnum::Move(ref move_node) = sequence_tree.nodes[*leaf_index].node_enum
+        else {
+            continue;
+        };
+        let leaf_game = move_node.r#move.game;
+        if !seen.insert(leaf_game) {
+            continue;
+        }
+        if let Ok(sequence) = sequence_tree.get_move_sequence(*leaf_index) {
+            turns.push((sequence, leaf_game));
+        }
+    }
+    turns
+}
+
+/// Plays single moves until the game ends, returning the Player's share of the result
+fn playout(mut game: Game, playout: Playout, rng: &mut Rng) -> f32 {
+    for _ in 0..MAX_PLAYOUT_MOVES {
+        if let Some(result) = game.player_result() {
+            return result;
+        }
+        let side = game.board.player_turn;
+        let mut candidates = Vec::new();
+        for pocket in 0..game.board.pockets() {
+            let mut next = game;
+            if next.play_move((pocket, side)).is_err() {
+                continue;
+            }
+            let priority = match playout {
+                Playout::Random => 0,
+                Playout::Greedy => {
+                    let free_turn =
+                        next.game_state == GameState::InProgress && next.board.player_turn == side;
+                    // free turns are worth more than any number of stones
+                    next.board.score(side) - game.board.score(side)
+                        + if free_turn { 1000 } else { 0 }
+                }
+            };
+            candidates.push((priority, next));
+        }
+        let Some(best) = candidates.iter().map(|(priority, _)| *priority).max() else {
+            break;
+        };
+        candidates.retain(|(priority, _)| *priority == best);
+        game = candidates[rng.below(candidates.len())].1;
+    }
+    match game.player_result() {
+        Some(result) => result,
+        None => {
+            let difference =
+                game.board.score(PlayerSide::Player) - game.board.score(PlayerSide::Opponent);
+            match difference.cmp(&0) {
+                std::cmp::Ordering::Greater => 1.0,
+                std::cmp::Ordering::Less => 0.0,
+                std::cmp::Ordering::Equal => 0.5,
+            }
+        }
+    }
+}
+
+impl MctsTree {
+    pub fn new(game: Game, config: MctsConfig) -> Self {
+        MctsTree {
+            nodes: vec![MctsNode::new(game, None, Vec::new())],
+            config,
+            rng: Rng(config.seed),
+            sequence_table: TranspositionTable::new(1 << 12),
+        }
+    }
+
+    /// The child of the node at the given index with the best UCT score for the player to move there
+    fn select_child(&self, index: MctsIndex) -> Option<MctsIndex> {
+        let node = &self.nodes[index];
+        let side = node.game.board.player_turn;
+        let log_visits = (node.visits.max(1) as f32).ln();
+        node.children.iter().copied().max_by(|&a, &b| {
+            let uct = |child: MctsIndex| {
+                let child = &self.nodes[child];
+                child.win_rate(side)
+                    + self.config.exploration * (log_visits / child.visits.max(1) as f32).sqrt()
+            };
+            uct(a).total_cmp(&uct(b))
+        })
+    }
+
+    /// Adds the most promising untried turn of the node at the given index as a new child and returns it, or
+    /// None if every turn has been tried (or the game is over)
+    fn expand(&mut self, index: MctsIndex) -> Option<MctsIndex> {
+        let game = self.nodes[index].game;
+        if game.game_state != GameState::InProgress {
+            return None;
+        }
+        if self.nodes[index].untried.is_none() {
+            let mut turns = distinct_turns(&game, &mut self.sequence_table);
+            // the turns that gain the most are at the end, so they are tried first
+            let side = game.board.player_turn;
+            turns.sort_by_key(|(_, turn_game)| {
+                let difference = turn_game.board.score(PlayerSide::Player)
+                    - turn_game.board.score(PlayerSide::Opponent);
+                match side {
+                    PlayerSide::Player => difference,
+                    PlayerSide::Opponent => -difference,
+                }
+            });
+            self.nodes[index].untried = Some(turns);
+        }
+        let untried = self.nodes[index].untried.as_mut()?;
+        if untried.is_empty() {
+            return None;
+        }
+        let (sequence, child_game) = untried.pop()?;
+        self.nodes
+            .push(MctsNode::new(child_game, Some(index), sequence));
+        let child_index = self.nodes.len() - 1;
+        self.nodes[index].children.push(child_index);
+        Some(child_index)
+    }
+
+    /// Runs one iteration: selection, expansion, playout and backpropagation
+    fn iterate(&mut self) {
+        let mut index = 0;
+        loop {
+            let node = &self.nodes[index];
+            let untried_left = node
+                .untried
+                .as_ref()
+                .is_none_or(|untried| !untried.is_empty());
+            // progressive widening: a node only gets another child once its visits allow it
+            let widen = node.children.len()
+                < ((node.visits + 1) as f32).powf(self.config.widening).ceil() as usize;
+            if untried_left && widen {
+                if let Some(child_index) = self.expand(index) {
+                    index = child_index;
+                    break;
+                }
+            }
+            match self.select_child(index) {
+                Some(child_index) => index = child_index,
+                // a finished game or a position without any turns
+                None => break,
+            }
+        }
+        let result = playout(self.nodes[index].game, self.config.playout, &mut self.rng);
+        let mut current = Some(index);
+        while let Some(index) = current {
+            self.nodes[index].visits += 1;
+            self.nodes[index].player_wins += result;
+            current = self.nodes[index].parent;
+        }
+    }
+
+    /// Search until the iteration or time budget runs out and return the most visited turn.
+    /// Returns `Error::NoMoves` if the game is over, or the budget ran out before any turn was tried.
+    pub fn search(&mut self) -> Result<MctsResult, Error> {
+        let start = Instant::now();
+        let mut iterations = 0;
+        while iterations < self.config.iterations {
+            if self
+                .config
+                .time_limit
+                .is_some_and(|limit| start.elapsed() >= limit)
+            {
+                break;
+            }
+            self.iterate();
+            iterations += 1;
+        }
+        let side = self.nodes[0].game.board.player_turn;
+        let best_child = self.nodes[0]
+            .children
+            .iter()
+            .copied()
+            .max_by_key(|&child_index| self.nodes[child_index].visits)
+            .ok_or(Error::NoMoves)?;
+        let child = &self.nodes[best_child];
+        Ok(MctsResult {
+            sequence: child.sequence.clone(),
+            win_rate: child.win_rate(side),
+            visits: child.visits,
+            iterations,
+            elapsed: start.elapsed(),
+        })
+    }
+
+    /// The number of nodes (whole turns) created during the search
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+}
@@ -1,96 +1,350 @@
-use crate::{solver::{SequenceNodeEnum, SequenceTreeIndex, SequenceTree, evaluate, EvalMethod}, game::{Game, PocketIndex, PlayerSide}};
+use std::collections::HashSet;
+
+use crate::{
+    error::Error,
+    eval::{Evaluator, StoreDifference},
+    game::{Game, GameOver, GameState, PlayerSide, PocketIndex, Winner},
+    solver::{SequenceNodeEnum, SequenceTree, SequenceTreeIndex},
+    transposition::{Bound, TranspositionTable},
+};
+
+/*
+The Game tree is stored the same way as the Sequence tree: a vector of nodes referenced by their index.
+Each edge in the game tree is a whole turn (a leaf of a SequenceTree), so a node's children are every
+distinct position the player to move can reach by the end of their turn.
+struct GameTree {
+    nodes: Vec<
+        struct GameTreeNode {
+            node_enum: enum NodeEnum {
+                Root(Game), // The position the search starts from
+                Move(
+                    struct InteriorNode {
+                        result: Game, // The position at the end of the turn
+                        parent: usize, // The index of the parent node in the nodes vector
+                        sequence: Vec<PocketIndex>, // The pockets played during the turn
+                    },
+                ),
+            },
+            children: Vec<usize>,
+            ...
+        },
+    >,
+}
+*/
+
+pub type GameTreeIndex = usize;
+
+/// Added to the evaluation of a finished game so that a win is always preferred over any unfinished position
+const WIN_BONUS: f32 = 1000.0;
 
-type GameTreeIndex = usize;
 struct GameTreeNode {
     /// The enum that determines the type of node
     node_enum: NodeEnum,
     /// The indices of the children nodes in the nodes vector
     children: Vec<GameTreeIndex>,
-    /// The depth of the node in the tree
+    /// The depth of the node in the tree (in turns, calculated from the root node)
     depth: usize,
     /// The evaluation of the node (used to determine the minimax value)
     evaluation: f32,
-    /// The minimax value of the node
+    /// The minimax value of the node (only a bound if the node's search was cut off)
     minimax_value: Option<f32>,
+    /// The index of the child with the best minimax value
+    best_child: Option<GameTreeIndex>,
 }
 
 struct InteriorNode {
+    /// The game state at the end of the turn
     result: Game,
+    /// The index of the parent node in the nodes vector
     parent: GameTreeIndex,
+    /// The pockets played during the turn, in order
     sequence: Vec<PocketIndex>,
 }
 
 enum NodeEnum {
     Root(Game),
-    Move(InteriorNode)
+    Move(InteriorNode),
 }
 
-struct GameTree {
+/// Tree of whole turns used to search several turns ahead with minimax, scoring the positions at
+/// the search horizon with `E`
+pub struct GameTree<E = StoreDifference> {
     nodes: Vec<GameTreeNode>,
+    /// Scores positions at the search horizon and orders each node's children
+    evaluator: E,
+    /// Statistics collected during the last search
+    stats: SearchStats,
+    /// Table used to skip repeated positions while generating the sequence tree of each turn
+    sequence_table: TranspositionTable,
+}
+
+/// Counters describing how much work alpha-beta pruning saved during a search
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchStats {
+    /// Number of nodes the search recursed into (including the root)
+    pub nodes_visited: usize,
+    /// Number of static evaluations used as minimax values (depth limit or game over)
+    pub leaf_evaluations: usize,
+    /// Number of times a node stopped searching its children early
+    pub cutoffs: usize,
+    /// Number of child turns skipped because of a cutoff
+    pub turns_pruned: usize,
+    /// Number of positions found in the transposition table
+    pub table_hits: usize,
+    /// Number of positions whose value was taken from the transposition table without searching
+    pub table_cutoffs: usize,
+}
+
+/// The result of a minimax search
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    /// The pockets to play this turn, in order
+    pub sequence: Vec<PocketIndex>,
+    /// The minimax value of the root position (positive favors the Player)
+    pub value: f32,
+    /// Pruning statistics of the search
+    pub stats: SearchStats,
+}
+
+/// Evaluate a game state, rewarding finished games with a bonus for the winner
+pub(crate) fn evaluate_game(game: &Game, evaluator: &impl Evaluator) -> f32 {
+    let evaluation = evaluator.evaluate(game);
+    match game.game_state {
+        GameState::InProgress => evaluation,
+        GameState::Over(GameOver::Win(Winner::Player))
+        | GameState::Over(GameOver::TechnicalWin(PlayerSide::Player)) => evaluation + WIN_BONUS,
+        GameState::Over(GameOver::Win(Winner::Opponent))
+        | GameState::Over(GameOver::TechnicalWin(PlayerSide::Opponent)) => evaluation - WIN_BONUS,
+        GameState::Over(GameOver::Win(Winner::Tie)) => evaluation,
+    }
 }
 
 impl GameTreeNode {
-    fn from_sequence_node(sequence_index: SequenceTreeIndex, sequence_tree: &SequenceTree, parent_index: GameTreeIndex, game_tree: &GameTree) -> Self {
-        let sequence_node = &sequence_tree.nodes[sequence_index];
-        let game = match sequence_node.node_enum {
-            SequenceNodeEnum::Move(ref move_node) => move_node.r#move.game.clone(),
-            _ => panic!("Sequence node is not a move node")
+    fn from_sequence_node<E: Evaluator>(
+        sequence_index: SequenceTreeIndex,
+        sequence_tree: &SequenceTree,
+        parent_index: GameTreeIndex,
+        game_tree: &GameTree<E>,
+    ) -> Option<Self> {
+        // only the root isn't a move node, and the root is never a leaf
+        let game = match sequence_tree.nodes[sequence_index].node_enum {
+            SequenceNodeEnum::Move(ref move_node) => move_node.r#move.game,
+            SequenceNodeEnum::Root(_) => return None,
         };
         let interior_node = InteriorNode {
             result: game,
             parent: parent_index,
-            sequence: sequence_node.path.clone(),
+            sequence: sequence_tree.get_move_sequence(sequence_index).ok()?,
         };
-        let children = Vec::new();
-        let evaluation = evaluate(&interior_node.result, &EvalMethod::ByDifference);
-        GameTreeNode {
+        Some(GameTreeNode {
             node_enum: NodeEnum::Move(interior_node),
-            children,
+            children: Vec::new(),
             depth: game_tree.nodes[parent_index].depth + 1,
-            evaluation,
+            evaluation: evaluate_game(&game, &game_tree.evaluator),
             minimax_value: None,
+            best_child: None,
+        })
+    }
+
+    fn game(&self) -> Game {
+        match self.node_enum {
+            NodeEnum::Root(game) => game,
+            NodeEnum::Move(ref interior_node) => interior_node.result,
         }
     }
 }
 
 impl GameTree {
-    fn new(mut self, sequence_tree: &SequenceTree) {
-        // Create the root node
-        let game = match sequence_tree.nodes[0].node_enum {
-            SequenceNodeEnum::Root(game) => game,
-            _ => panic!("First node is not a root node")
-        };
+    /// A tree that scores positions by the difference between the stores
+    pub fn new(game: Game) -> Self {
+        GameTree::with_evaluator(game, StoreDifference)
+    }
+}
+
+impl<E: Evaluator> GameTree<E> {
+    /// A tree that scores positions with `evaluator`.
+    /// Values in a transposition table are only meaningful to searches with the same evaluator,
+    /// so trees with different evaluators shouldn't share a table.
+    pub fn with_evaluator(game: Game, evaluator: E) -> Self {
         let root_node = GameTreeNode {
             node_enum: NodeEnum::Root(game),
             children: Vec::new(),
             depth: 0,
-            evaluation: evaluate(&game, &EvalMethod::ByDifference),
+            evaluation: evaluate_game(&game, &evaluator),
             minimax_value: None,
+            best_child: None,
         };
-        self.nodes.push(root_node);
-        // Create the first layer of nodes
-        for leaf_index in &sequence_tree.leaf_nodes {
-            let game_tree_node = GameTreeNode::from_sequence_node(*leaf_index, sequence_tree, 0, &self);
-            self.nodes.push(game_tree_node);
-            let index = self.nodes.len() - 1;
-            self.nodes[0].children.push(index);
+        GameTree {
+            nodes: vec![root_node],
+            evaluator,
+            stats: SearchStats::default(),
+            sequence_table: TranspositionTable::new(1 << 12),
         }
     }
 
-    /// Creates 1 layer of children of the node at the given index
-    fn create_children(mut self, index: GameTreeIndex, player_side: PlayerSide) {
-        let node = &self.nodes[index];
-        let mut sequence_tree = SequenceTree::new(match node.node_enum {
-            NodeEnum::Root(ref game) => game.clone(),
-            NodeEnum::Move(ref interior_node) => interior_node.result.clone(),
-        });
-        sequence_tree.generate_tree(player_side, None);
+    /// Creates 1 layer of children of the node at the given index, one for each distinct position
+    /// the player to move can reach at the end of their turn.
+    /// The children are ordered best first for the player to move, by the same evaluation (and win
+    /// bonus) the search scores them with, so that alpha-beta pruning cuts off as early as possible
+    /// and a turn that wins the game is always tried first.
+    fn create_children(&mut self, index: GameTreeIndex) {
+        let game = self.nodes[index].game();
+        let mut sequence_tree = SequenceTree::new(game);
+        sequence_tree.generate_tree_with_table(game.board.player_turn, &mut self.sequence_table);
+        // Different sequences often end on the same position, only the first one found is kept
+        let mut seen = HashSet::new();
+        let mut children = Vec::new();
         for leaf_index in &sequence_tree.leaf_nodes {
-            let game_tree_node = GameTreeNode::from_sequence_node(*leaf_index, &sequence_tree, index, &self);
+            let Some(game_tree_node) =
+                GameTreeNode::from_sequence_node(*leaf_index, &sequence_tree, index, self)
+            else {
+                continue;
+            };
+            if !seen.insert(game_tree_node.game()) {
+                continue;
+            }
+            let evaluation = game_tree_node.evaluation;
             self.nodes.push(game_tree_node);
-            let index = self.nodes.len() - 1;
-            self.nodes[index].children.push(index);
+            children.push((self.nodes.len() - 1, evaluation));
+        }
+        // sort_by is stable, so turns with equal scores keep the order they were generated in
+        match game.board.player_turn {
+            PlayerSide::Player => children.sort_by(|a, b| b.1.total_cmp(&a.1)),
+            PlayerSide::Opponent => children.sort_by(|a, b| a.1.total_cmp(&b.1)),
+        }
+        self.nodes[index].children = children.into_iter().map(|(index, _)| index).collect();
+    }
+
+    /// The turn played to reach the node at the given index, which is empty for the root
+    fn sequence(&self, index: GameTreeIndex) -> Vec<PocketIndex> {
+        match self.nodes[index].node_enum {
+            NodeEnum::Move(ref interior_node) => interior_node.sequence.clone(),
+            NodeEnum::Root(_) => Vec::new(),
         }
-        
     }
-}
\ No newline at end of file
+
+    /// Recursively computes the minimax value of the node at the given index, searching `depth` turns ahead.
+    /// Children are only searched while their value can still change the result within the (alpha, beta) window.
+    fn minimax(
+        &mut self,
+        index: GameTreeIndex,
+        depth: usize,
+        mut alpha: f32,
+        mut beta: f32,
+        table: &mut TranspositionTable,
+    ) -> f32 {
+        self.stats.nodes_visited += 1;
+        let game = self.nodes[index].game();
+        if depth == 0 || game.game_state != GameState::InProgress {
+            self.stats.leaf_evaluations += 1;
+            let value = self.nodes[index].evaluation;
+            self.nodes[index].minimax_value = Some(value);
+            return value;
+        }
+        let mut table_turn = None;
+        if let Some(entry) = table.probe(&game) {
+            self.stats.table_hits += 1;
+            // The root always needs its children searched to know which turn to play
+            if index != 0 && entry.depth >= depth {
+                let usable = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => entry.value >= beta,
+                    Bound::Upper => entry.value <= alpha,
+                };
+                if usable {
+                    self.stats.table_cutoffs += 1;
+                    self.nodes[index].minimax_value = Some(entry.value);
+                    return entry.value;
+                }
+            }
+            table_turn = Some(entry.best_turn.clone());
+        }
+        if self.nodes[index].children.is_empty() {
+            self.create_children(index);
+        }
+        let mut children = self.nodes[index].children.clone();
+        // A position with no legal turns can only be evaluated statically
+        if children.is_empty() {
+            self.stats.leaf_evaluations += 1;
+            let value = self.nodes[index].evaluation;
+            self.nodes[index].minimax_value = Some(value);
+            return value;
+        }
+        // The best turn from an earlier search is the most likely to cause a cutoff, so it goes first
+        if let Some(table_turn) = table_turn {
+            if let Some(position) = children
+                .iter()
+                .position(|&child_index| self.sequence(child_index) == table_turn)
+            {
+                let child_index = children.remove(position);
+                children.insert(0, child_index);
+            }
+        }
+        let (original_alpha, original_beta) = (alpha, beta);
+        let maximizing = game.board.player_turn == PlayerSide::Player;
+        let mut best_value = match maximizing {
+            true => f32::NEG_INFINITY,
+            false => f32::INFINITY,
+        };
+        let mut best_child = None;
+        for (searched, child_index) in children.iter().enumerate() {
+            let value = self.minimax(*child_index, depth - 1, alpha, beta, table);
+            if maximizing && value > best_value {
+                best_value = value;
+                best_child = Some(*child_index);
+                alpha = alpha.max(value);
+            } else if !maximizing && value < best_value {
+                best_value = value;
+                best_child = Some(*child_index);
+                beta = beta.min(value);
+            }
+            if alpha >= beta {
+                self.stats.cutoffs += 1;
+                self.stats.turns_pruned += children.len() - searched - 1;
+                break;
+            }
+        }
+        self.nodes[index].minimax_value = Some(best_value);
+        self.nodes[index].best_child = best_child;
+        let bound = if best_value <= original_alpha {
+            Bound::Upper
+        } else if best_value >= original_beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        let best_turn = best_child
+            .map(|child_index| self.sequence(child_index))
+            .unwrap_or_default();
+        table.store(game, depth, bound, best_value, best_turn);
+        best_value
+    }
+
+    /// Search `depth` whole turns ahead and return the best turn for the player to move at the root.
+    /// Results are cached in and reused from `table`; keeping the same table between turns lets
+    /// later searches start from what earlier ones found.
+    /// Returns `Error::NoMoves` if the game is over or `depth` is 0, so there is no turn to choose.
+    pub fn search(
+        &mut self,
+        depth: usize,
+        table: &mut TranspositionTable,
+    ) -> Result<SearchResult, Error> {
+        self.stats = SearchStats::default();
+        table.new_search();
+        let value = self.minimax(0, depth, f32::NEG_INFINITY, f32::INFINITY, table);
+        let child_index = self.nodes[0].best_child.ok_or(Error::NoMoves)?;
+        if let NodeEnum::Move(ref interior_node) = self.nodes[child_index].node_enum {
+            debug_assert_eq!(interior_node.parent, 0);
+        }
+        Ok(SearchResult {
+            sequence: self.sequence(child_index),
+            value,
+            stats: self.stats,
+        })
+    }
+
+    /// The number of nodes (whole turns) created during the search
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+}
@@ -0,0 +1,247 @@
+use std::io::{ErrorKind, Write};
+
+use crate::error::Error;
+use crate::eval::{Evaluator, StoreDifference};
+use crate::game::{Game, GameState, PocketIndex};
+use crate::mcts::{MctsConfig, MctsTree};
+use crate::minimax::GameTree;
+#[cfg(feature = "qlearning")]
+use crate::qlearning_move::QPolicy;
+use crate::rng::Rng;
+use crate::solver::SequenceTree;
+use crate::transposition::TranspositionTable;
+
+/// Anything that can choose a turn: a person at the terminal, a search engine or a learned policy.
+/// Any two players can be pitted against each other by asking whichever one is to move for a turn
+/// and playing it.
+///
+/// ```
+/// use mancala_rs::game::{Game, GameState, PlayerSide};
+/// use mancala_rs::player::{MinimaxPlayer, Player, RandomPlayer};
+///
+/// let mut players: [Box<dyn Player>; 2] =
+///     [Box::new(MinimaxPlayer::new(2)), Box::new(RandomPlayer::new(1))];
+/// let mut game = Game::default();
+/// while game.game_state == GameState::InProgress {
+///     let mover = match game.board.player_turn {
+///         PlayerSide::Player => &mut players[0],
+///         PlayerSide::Opponent => &mut players[1],
+///     };
+///     for pocket in mover.choose_turn(&game)? {
+///         game.play_move((pocket, game.board.player_turn))?;
+///     }
+/// }
+/// assert!(game.player_result().is_some());
+/// # Ok::<(), mancala_rs::error::Error>(())
+/// ```
+pub trait Player {
+    /// A short description of the player, such as "Minimax (2 turns)"
+    fn name(&self) -> String;
+
+    /// The pockets to play this turn, in order, for the side to move in `game`. Free turns are
+    /// included, so the turn passes (or the game ends) after the last pocket.
+    /// Returns `Error::NoMoves` if the game is over, or another error if the player gave up.
+    fn choose_turn(&mut self, game: &Game) -> Result<Vec<PocketIndex>, Error>;
+}
+
+/// Plays single pockets with `choose_pocket` until the turn passes, the game ends or no pocket is
+/// chosen. Returns `Error::NoMoves` if not even the first pocket is chosen.
+fn play_pockets(
+    game: &Game,
+    mut choose_pocket: impl FnMut(&Game) -> Option<PocketIndex>,
+) -> Result<Vec<PocketIndex>, Error> {
+    let side = game.board.player_turn;
+    let mut game = *game;
+    let mut turn = Vec::new();
+    while game.game_state == GameState::InProgress && game.board.player_turn == side {
+        let Some(pocket) = choose_pocket(&game) else {
+            break;
+        };
+        if game.play_move((pocket, side)).is_err() {
+            break;
+        }
+        turn.push(pocket);
+    }
+    match turn.is_empty() {
+        true => Err(Error::NoMoves),
+        false => Ok(turn),
+    }
+}
+
+/// The pockets the side to move can play
+fn playable_pockets(game: &Game) -> Vec<PocketIndex> {
+    (0..game.board.pockets())
+        .filter(|pocket| game.board.get_stones((*pocket, game.board.player_turn)) > 0)
+        .collect()
+}
+
+/// A person entering pockets on stdin, one move at a time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HumanPlayer;
+
+impl Player for HumanPlayer {
+    fn name(&self) -> String {
+        "Human".to_string()
+    }
+
+    /// Shows the game and asks for pockets until the turn passes. Gives up once stdin is closed.
+    fn choose_turn(&mut self, game: &Game) -> Result<Vec<PocketIndex>, Error> {
+        if game.game_state != GameState::InProgress {
+            return Err(Error::NoMoves);
+        }
+        let side = game.board.player_turn;
+        let mut game = *game;
+        let mut turn = Vec::new();
+        while game.game_state == GameState::InProgress && game.board.player_turn == side {
+            println!("{:?}", game);
+            println!("Enter the pocket to play:");
+            print!("> ");
+            let mut input = String::new();
+            std::io::stdout().flush()?;
+            if std::io::stdin().read_line(&mut input)? == 0 {
+                return Err(Error::Io(ErrorKind::UnexpectedEof.into()));
+            }
+            let Ok(pocket) = input.trim().parse::<PocketIndex>() else {
+                println!("Invalid input");
+                continue;
+            };
+            match game.play_move((pocket, side)) {
+                Ok(_) => turn.push(pocket),
+                Err(error) => println!("Invalid move: {}", error),
+            }
+        }
+        Ok(turn)
+    }
+}
+
+/// Plays the turn with the best evaluation at the end of the turn, without looking at the
+/// opponent's replies (searching 1 turn ahead)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GreedyPlayer<E = StoreDifference> {
+    evaluator: E,
+}
+
+impl GreedyPlayer {
+    /// A player that scores the most points this turn
+    pub fn new() -> Self {
+        GreedyPlayer::with_evaluator(StoreDifference)
+    }
+}
+
+impl<E: Evaluator> GreedyPlayer<E> {
+    pub fn with_evaluator(evaluator: E) -> Self {
+        GreedyPlayer { evaluator }
+    }
+}
+
+impl<E: Evaluator> Player for GreedyPlayer<E> {
+    fn name(&self) -> String {
+        "Greedy".to_string()
+    }
+
+    fn choose_turn(&mut self, game: &Game) -> Result<Vec<PocketIndex>, Error> {
+        let side = game.board.player_turn;
+        let mut tree = SequenceTree::new(*game);
+        tree.generate_tree_parallel(side);
+        tree.get_best_sequence(&self.evaluator, true, &side)
+    }
+}
+
+/// Searches whole turns ahead with minimax, keeping its transposition table between turns
+pub struct MinimaxPlayer<E = StoreDifference> {
+    evaluator: E,
+    /// Number of whole turns to search ahead
+    depth: usize,
+    table: TranspositionTable,
+}
+
+impl MinimaxPlayer {
+    /// A player searching `depth` turns ahead, scoring positions by the store difference
+    pub fn new(depth: usize) -> Self {
+        MinimaxPlayer::with_evaluator(depth, StoreDifference)
+    }
+}
+
+impl<E: Evaluator + Clone> MinimaxPlayer<E> {
+    pub fn with_evaluator(depth: usize, evaluator: E) -> Self {
+        MinimaxPlayer {
+            evaluator,
+            depth,
+            table: TranspositionTable::default(),
+        }
+    }
+}
+
+impl<E: Evaluator + Clone> Player for MinimaxPlayer<E> {
+    fn name(&self) -> String {
+        format!("Minimax ({} turns)", self.depth)
+    }
+
+    fn choose_turn(&mut self, game: &Game) -> Result<Vec<PocketIndex>, Error> {
+        let result = GameTree::with_evaluator(*game, self.evaluator.clone())
+            .search(self.depth, &mut self.table)?;
+        Ok(result.sequence)
+    }
+}
+
+/// Searches with Monte Carlo tree search, starting a new tree every turn
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MctsPlayer {
+    pub config: MctsConfig,
+}
+
+impl MctsPlayer {
+    pub fn new(config: MctsConfig) -> Self {
+        MctsPlayer { config }
+    }
+}
+
+impl Player for MctsPlayer {
+    fn name(&self) -> String {
+        format!("MCTS ({} iterations)", self.config.iterations)
+    }
+
+    fn choose_turn(&mut self, game: &Game) -> Result<Vec<PocketIndex>, Error> {
+        Ok(MctsTree::new(*game, self.config).search()?.sequence)
+    }
+}
+
+/// Plays a random playable pocket every move
+#[derive(Debug, Clone)]
+pub struct RandomPlayer {
+    rng: Rng,
+}
+
+impl RandomPlayer {
+    /// A player whose moves are decided by `seed`; the same seed always plays the same moves
+    pub fn new(seed: u64) -> Self {
+        RandomPlayer { rng: Rng(seed) }
+    }
+}
+
+impl Player for RandomPlayer {
+    fn name(&self) -> String {
+        "Random".to_string()
+    }
+
+    fn choose_turn(&mut self, game: &Game) -> Result<Vec<PocketIndex>, Error> {
+        play_pockets(game, |game| {
+            let pockets = playable_pockets(game);
+            match pockets.is_empty() {
+                true => None,
+                false => Some(pockets[self.rng.below(pockets.len())]),
+            }
+        })
+    }
+}
+
+#[cfg(feature = "qlearning")]
+impl Player for QPolicy {
+    fn name(&self) -> String {
+        "Q-learning".to_string()
+    }
+
+    fn choose_turn(&mut self, game: &Game) -> Result<Vec<PocketIndex>, Error> {
+        QPolicy::choose_turn(self, game)
+    }
+}
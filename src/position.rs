@@ -0,0 +1,199 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::game::{Board, Game, InvalidBoardError, PlayerSide};
+use crate::rules::{Rules, Sweep, Variant};
+
+/*
+Positions are written on a single line, with each side's side pockets separated by commas, then a '/'
+and the side's store. The player's side comes first, then the opponent's, then whose turn it is.
+The README example position is written as
+5,3,2,0,1,7/12 7,2,4,9,0,2/9 P
+A game is written as its board's position followed by the variant it is played with, and the sweep
+when it isn't the variant's usual one
+5,3,2,0,1,7/12 7,2,4,9,0,2/9 P Kalah
+5,3,2,0,1,7/12 7,2,4,9,0,2/9 P Kalah nosweep
+When reading a game, the variant can be left out to use the default rules.
+*/
+
+/// Error type for reading a position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsePositionError {
+    /// The position doesn't have both sides and the turn
+    MissingField,
+    /// There is text after the end of the position
+    TrailingField(String),
+    /// A side is not written as comma separated pockets, a '/' and the store
+    MalformedSide(String),
+    /// A pocket's stone count is not a number
+    InvalidStones(String),
+    /// The stones don't make a board that can be played on
+    InvalidBoard(InvalidBoardError),
+    /// The turn is not P or O
+    InvalidTurn(String),
+    /// The variant is not one of the known variants
+    InvalidVariant(String),
+    /// The sweep is not sweep or nosweep
+    InvalidSweep(String),
+}
+
+impl Display for ParsePositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParsePositionError::MissingField => {
+                write!(f, "a position needs both sides and the turn")
+            }
+            ParsePositionError::TrailingField(field) => {
+                write!(f, "unexpected text after the position: {}", field)
+            }
+            ParsePositionError::MalformedSide(side) => write!(
+                f,
+                "a side should be written as its pockets, a '/' and its store: {}",
+                side
+            ),
+            ParsePositionError::InvalidStones(stones) => {
+                write!(f, "invalid number of stones: {}", stones)
+            }
+            ParsePositionError::InvalidBoard(error) => write!(f, "{}", error),
+            ParsePositionError::InvalidTurn(turn) => {
+                write!(f, "the turn should be P or O, not {}", turn)
+            }
+            ParsePositionError::InvalidVariant(variant) => {
+                write!(f, "unknown variant: {}", variant)
+            }
+            ParsePositionError::InvalidSweep(sweep) => {
+                write!(f, "the sweep should be sweep or nosweep, not {}", sweep)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParsePositionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParsePositionError::InvalidBoard(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Reads one side as its side pockets followed by its store
+fn parse_side(side: &str) -> Result<Vec<i32>, ParsePositionError> {
+    let (pockets, store) = side
+        .split_once('/')
+        .ok_or_else(|| ParsePositionError::MalformedSide(side.to_string()))?;
+    let parse_stones = |stones: &str| {
+        stones
+            .parse::<i32>()
+            .map_err(|_| ParsePositionError::InvalidStones(stones.to_string()))
+    };
+    let mut stones = pockets
+        .split(',')
+        .map(parse_stones)
+        .collect::<Result<Vec<i32>, _>>()?;
+    stones.push(parse_stones(store)?);
+    Ok(stones)
+}
+
+/// Reads the board from the start of the fields, leaving the rest for the caller
+fn parse_board<'a>(
+    fields: &mut impl Iterator<Item = &'a str>,
+) -> Result<Board, ParsePositionError> {
+    let mut next_field = || fields.next().ok_or(ParsePositionError::MissingField);
+    let player_pockets = parse_side(next_field()?)?;
+    let opponent_pockets = parse_side(next_field()?)?;
+    let player_turn = match next_field()? {
+        "P" | "p" => PlayerSide::Player,
+        "O" | "o" => PlayerSide::Opponent,
+        turn => return Err(ParsePositionError::InvalidTurn(turn.to_string())),
+    };
+    Board::free_form(&player_pockets, &opponent_pockets, player_turn)
+        .map_err(ParsePositionError::InvalidBoard)
+}
+
+/// Writes one side as its side pockets followed by its store
+fn write_side(
+    f: &mut std::fmt::Formatter<'_>,
+    board: &Board,
+    side: PlayerSide,
+) -> std::fmt::Result {
+    let pockets = board
+        .side_pockets(side)
+        .iter()
+        .map(|stones| stones.to_string())
+        .collect::<Vec<String>>()
+        .join(",");
+    write!(f, "{}/{}", pockets, board.score(side))
+}
+
+impl Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_side(f, self, PlayerSide::Player)?;
+        write!(f, " ")?;
+        write_side(f, self, PlayerSide::Opponent)?;
+        match self.player_turn {
+            PlayerSide::Player => write!(f, " P"),
+            PlayerSide::Opponent => write!(f, " O"),
+        }
+    }
+}
+
+impl FromStr for Board {
+    type Err = ParsePositionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split_whitespace();
+        let board = parse_board(&mut fields)?;
+        match fields.next() {
+            Some(field) => Err(ParsePositionError::TrailingField(field.to_string())),
+            None => Ok(board),
+        }
+    }
+}
+
+impl Display for Game {
+    /// Writes the position and the variant. Rules that don't match any variant are written as
+    /// "Custom", which can't be read back
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.board)?;
+        match self.rules.base_variant() {
+            Some(variant) if variant.rules().sweep == self.rules.sweep => {
+                write!(f, " {}", variant)
+            }
+            Some(variant) => match self.rules.sweep {
+                Sweep::ToOwner => write!(f, " {} sweep", variant),
+                Sweep::None => write!(f, " {} nosweep", variant),
+            },
+            None => write!(f, " Custom"),
+        }
+    }
+}
+
+impl FromStr for Game {
+    type Err = ParsePositionError;
+
+    /// Reads a position optionally followed by the variant and sweep, using the default rules if
+    /// the variant is left out
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split_whitespace();
+        let board = parse_board(&mut fields)?;
+        let mut rules = match fields.next() {
+            Some(name) => Variant::ALL
+                .into_iter()
+                .find(|variant| variant.to_string().eq_ignore_ascii_case(name))
+                .ok_or_else(|| ParsePositionError::InvalidVariant(name.to_string()))?
+                .rules(),
+            None => Rules::default(),
+        };
+        match fields.next() {
+            Some("sweep") => rules.sweep = Sweep::ToOwner,
+            Some("nosweep") => rules.sweep = Sweep::None,
+            Some(sweep) => return Err(ParsePositionError::InvalidSweep(sweep.to_string())),
+            None => {}
+        }
+        match fields.next() {
+            Some(field) => Err(ParsePositionError::TrailingField(field.to_string())),
+            None => Ok(Game::with_rules(board, rules)),
+        }
+    }
+}
@@ -1,29 +1,628 @@
-use rurel::mdp::{State, Agent};
-use crate::game::{Game, PlayerSide, PocketIndex};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::game::{Board, Game, GameState, PlayerSide, PocketIndex, MAX_POCKETS};
+use crate::rng::Rng;
+use crate::rules::{Rules, Sweep, Variant};
+use rurel::mdp::{Agent, State};
+use rurel::strategy::explore::ExplorationStrategy;
+use rurel::strategy::learn::{LearningStrategy, QLearning};
+use rurel::strategy::terminate::TerminationStrategy;
+use rurel::AgentTrainer;
+
+/*
+Q-learning treats the game as a single player process: the learner plays one side, and the other
+side's moves are part of the environment, so rurel's "best next value" is always the learner's own.
+Positions are stored from the view of the side to move, with the board mirrored when the Opponent
+is to move, so one table learns both sides. The learner alternates sides between episodes and its
+opponent plays the table as it was at the last snapshot (self-play against a slightly older self).
+The learner itself picks its moves from the live table, one rurel training step at a time.
+
+Each step is rewarded with the change in the learner's store difference (as a fraction of the
+stones on the board) that the move and the opponent's replies made, and the last step also with
+the result of the game. rurel only shows a reward the position reached, so `Game::reward` is the
+potential of that position (plus the result once the game is over) and `StepReward` subtracts the
+potential of the position the step started from.
+
+Saved tables are binary files, with every number little endian:
+    magic: b"MQT\0"
+    version: u8 // QTABLE_VERSION, files from newer versions are rejected
+    variant: u8 // index in Variant::ALL
+    sweep: u8 // 0 for Sweep::None, 1 for Sweep::ToOwner
+    pockets: u8 // side pockets on each side
+    stones: u32 // stones each side pocket started with
+    positions: u64
+    then for each position, in order of its pockets:
+        stones: varint for each pocket of the Player's side including the store, then the Opponent's
+        actions: u8
+        then for each action: pocket: u8, value: f64
+The varints are LEB128: 7 bits at a time, lowest first, with the high bit set on every byte but the last.
+Every position is from the view of the side to move, so the turn is always the Player's and isn't saved.
+*/
+
+/// The learned value of each pocket in each position, from the view of the side to move
+pub type QTable = HashMap<Game, HashMap<PocketIndex, f64>>;
+
+/// Reward for winning a game; a loss is the negative and a tie is 0
+const WIN_REWARD: f64 = 1.0;
+
+/// Episodes still going after this many of the learner's moves are stopped
+const MAX_EPISODE_MOVES: usize = 1000;
+
+/// The first bytes of every saved table
+const QTABLE_MAGIC: [u8; 4] = *b"MQT\0";
+
+/// The version of the saved table format written by `QPolicy::to_bytes`
+pub const QTABLE_VERSION: u8 = 1;
 
 impl State for Game {
     type A = PocketIndex;
     fn actions(&self) -> Vec<Self::A> {
-        (0..6)
-        .filter(|pocket: &PocketIndex| self.board.get_stones((*pocket, self.board.player_turn)) > 0)
-        .collect()
+        if self.game_state != GameState::InProgress {
+            return Vec::new();
+        }
+        (0..self.board.pockets())
+            .filter(|pocket: &PocketIndex| {
+                self.board.get_stones((*pocket, self.board.player_turn)) > 0
+            })
+            .collect()
     }
 
+    /// The potential of this position for the Player, plus the result once the game is over.
+    /// The step's reward is this minus the potential the step started from (see `StepReward`).
     fn reward(&self) -> f64 {
-        todo!()
+        let result = match self.player_result() {
+            Some(result) => WIN_REWARD * (2.0 * result as f64 - 1.0),
+            None => 0.0,
+        };
+        potential(self) + result
+    }
+}
+
+/// The Player's store difference as a fraction of the stones on the board
+fn potential(game: &Game) -> f64 {
+    let difference = game.board.score(PlayerSide::Player) - game.board.score(PlayerSide::Opponent);
+    difference as f64 / game.board.total_stones().max(1) as f64
+}
+
+/// Q-learning on the change in potential over a step rather than the potential reached
+struct StepReward<'a> {
+    learning: &'a QLearning,
+    /// The potential of the position the step started from
+    start: f64,
+}
+
+impl LearningStrategy<Game> for StepReward<'_> {
+    fn value(
+        &self,
+        new_action_values: &Option<&HashMap<PocketIndex, f64>>,
+        current_value: &Option<&f64>,
+        received_reward: f64,
+    ) -> f64 {
+        LearningStrategy::<Game>::value(
+            self.learning,
+            new_action_values,
+            current_value,
+            received_reward - self.start,
+        )
+    }
+}
+
+/// The game as seen by `side`, who is always the Player in the result
+fn view(game: &Game, side: PlayerSide) -> Game {
+    match side {
+        PlayerSide::Player => *game,
+        PlayerSide::Opponent => game.mirrored(),
+    }
+}
+
+/// The pocket with the highest learned value in a position seen from the side to move.
+/// Equal values go to the lowest pocket so that the choice doesn't depend on hash order.
+fn best_pocket(table: &QTable, state: &Game) -> Option<PocketIndex> {
+    table
+        .get(state)?
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(b.1).then(b.0.cmp(a.0)))
+        .map(|(pocket, _)| *pocket)
+}
+
+/// Settings for `QPolicy::train`
+#[derive(Debug, Clone, Copy)]
+pub struct QLearningConfig {
+    /// Number of self-play games to learn from
+    pub episodes: usize,
+    /// How far each value moves towards a new estimate (alpha)
+    pub learning_rate: f64,
+    /// How much later rewards count compared to the next one (gamma)
+    pub discount: f64,
+    /// Chance that the learner plays a random pocket instead of the best one it knows (epsilon)
+    pub exploration: f64,
+    /// Number of episodes between updates of the opponent's copy of the table
+    pub snapshot_interval: usize,
+    /// Seed for the random moves; the same seed and settings always learn the same table
+    pub seed: u64,
+}
+
+impl Default for QLearningConfig {
+    fn default() -> Self {
+        QLearningConfig {
+            episodes: 10000,
+            learning_rate: 0.2,
+            discount: 0.9,
+            exploration: 0.2,
+            snapshot_interval: 500,
+            seed: 1,
+        }
     }
 }
 
-struct MyAgent {
+/// The agent rurel trains: it plays `side`, and every move of the other side is answered from
+/// the opponent's table before the learner sees the next position
+pub struct TrainingAgent<'a> {
+    /// The game being played
+    game: Game,
+    /// The side the learner plays
+    side: PlayerSide,
+    /// The game as the learner sees it
     state: Game,
+    /// The table the opponent plays from
+    opponent: &'a QTable,
+    /// Used for the opponent's moves in positions it hasn't learned
+    rng: Rng,
 }
 
-impl Agent<Game> for MyAgent {
+impl<'a> TrainingAgent<'a> {
+    /// An agent playing `side` from `game`. If it's the other side's turn, the opponent moves first.
+    pub fn new(game: Game, side: PlayerSide, opponent: &'a QTable, seed: u64) -> Self {
+        let mut agent = TrainingAgent {
+            game,
+            side,
+            state: view(&game, side),
+            opponent,
+            rng: Rng(seed),
+        };
+        agent.play_opponent();
+        agent
+    }
+
+    /// Plays the opponent's moves until it's the learner's turn or the game is over
+    fn play_opponent(&mut self) {
+        while self.game.game_state == GameState::InProgress
+            && self.game.board.player_turn != self.side
+        {
+            let opponent_view = view(&self.game, self.game.board.player_turn);
+            let actions = opponent_view.actions();
+            if actions.is_empty() {
+                break;
+            }
+            let pocket = best_pocket(self.opponent, &opponent_view)
+                .unwrap_or_else(|| actions[self.rng.below(actions.len())]);
+            if self
+                .game
+                .play_move((pocket, self.game.board.player_turn))
+                .is_err()
+            {
+                break;
+            }
+        }
+        self.state = view(&self.game, self.side);
+    }
+}
+
+impl Agent<Game> for TrainingAgent<'_> {
     fn current_state(&self) -> &Game {
         &self.state
     }
 
-    fn take_action(&mut self, action: &usize) {
-        self.state.play_move((*action, self.state.board.player_turn)).expect("Invalid move");
+    /// Plays the learner's pocket, then the opponent's replies. Actions always come from
+    /// `Game::actions`, so they are legal.
+    fn take_action(&mut self, action: &PocketIndex) {
+        if self.game.play_move((*action, self.side)).is_ok() {
+            self.play_opponent();
+        }
+    }
+}
+
+/// Epsilon-greedy exploration: usually the best pocket the learner knows, sometimes a random one
+struct EpsilonGreedy {
+    epsilon: f64,
+    rng: Rng,
+}
+
+impl EpsilonGreedy {
+    /// The pocket to play in `state`, which must have a pocket to play, using the live `table`
+    fn choose(&mut self, table: &QTable, state: &Game) -> PocketIndex {
+        let actions = state.actions();
+        let explore = self.rng.unit() < self.epsilon;
+        match best_pocket(table, state) {
+            Some(pocket) if !explore => pocket,
+            _ => actions[self.rng.below(actions.len())],
+        }
+    }
+}
+
+/// Plays a pocket chosen before the training step, since rurel's exploration strategies can't
+/// see the trainer's values
+struct ChosenPocket(PocketIndex);
+
+impl ExplorationStrategy<Game> for ChosenPocket {
+    fn pick_action(&self, agent: &mut dyn Agent<Game>) -> PocketIndex {
+        agent.take_action(&self.0);
+        self.0
+    }
+}
+
+/// Stops rurel's training loop after a single step
+struct SingleStep;
+
+impl TerminationStrategy<Game> for SingleStep {
+    fn should_stop(&mut self, _: &Game) -> bool {
+        true
+    }
+}
+
+/// The game a table was learned for, which is saved in the header of the table's file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QTableHeader {
+    pub rules: Rules,
+    /// Number of side pockets on each side
+    pub pockets: usize,
+    /// Number of stones each side pocket started with
+    pub stones: i32,
+}
+
+impl QTableHeader {
+    /// The header for a policy trained from `start`
+    pub fn for_game(start: &Game) -> Self {
+        let pockets = start.board.pockets();
+        QTableHeader {
+            rules: start.rules,
+            pockets,
+            stones: start.board.total_stones() / (2 * pockets as i32),
+        }
+    }
+}
+
+/// Error type for saving or loading a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QTableError {
+    /// The file doesn't start with the saved table magic
+    NotAQTable,
+    /// The file was written by a newer version of the format
+    UnsupportedVersion(u8),
+    /// The variant byte isn't one of the known variants
+    UnknownVariant(u8),
+    /// The sweep byte isn't 0 or 1
+    UnknownSweep(u8),
+    /// The number of pockets can't be played on
+    InvalidPockets(usize),
+    /// A saved position isn't a board that can be played on
+    InvalidPosition,
+    /// An action isn't one of the board's pockets
+    InvalidPocket(usize),
+    /// The file ends in the middle of the table
+    Truncated,
+    /// There is more data after the last position
+    TrailingData,
+    /// The policy hasn't been trained, so there is no game to write in the header
+    Untrained,
+    /// The rules don't match a variant, so they can't be written in the header
+    CustomRules,
+}
+
+impl Display for QTableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QTableError::NotAQTable => write!(f, "not a saved Q-table"),
+            QTableError::UnsupportedVersion(version) => write!(
+                f,
+                "the table was saved with format version {}, but only versions up to {} can be read",
+                version, QTABLE_VERSION
+            ),
+            QTableError::UnknownVariant(variant) => write!(f, "unknown variant: {}", variant),
+            QTableError::UnknownSweep(sweep) => write!(f, "unknown sweep: {}", sweep),
+            QTableError::InvalidPockets(pockets) => {
+                write!(f, "invalid number of pockets: {}", pockets)
+            }
+            QTableError::InvalidPosition => write!(f, "a saved position isn't a valid board"),
+            QTableError::InvalidPocket(pocket) => write!(f, "invalid pocket: {}", pocket),
+            QTableError::Truncated => write!(f, "the table ends too early"),
+            QTableError::TrailingData => write!(f, "unexpected data after the table"),
+            QTableError::Untrained => write!(f, "the policy hasn't been trained"),
+            QTableError::CustomRules => {
+                write!(f, "only tables for one of the variants can be saved")
+            }
+        }
+    }
+}
+
+impl std::error::Error for QTableError {}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Reads a saved table from the start, failing with `Truncated` at the end of the bytes
+struct TableReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl TableReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], QTableError> {
+        let (taken, rest) = self
+            .bytes
+            .split_first_chunk::<N>()
+            .ok_or(QTableError::Truncated)?;
+        self.bytes = rest;
+        Ok(*taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, QTableError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn varint(&mut self) -> Result<u32, QTableError> {
+        let mut value = 0u32;
+        for shift in (0..32).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(QTableError::InvalidPosition)
+    }
+}
+
+/// A move chooser that plays the pockets with the highest learned values
+#[derive(Clone, Default)]
+pub struct QPolicy {
+    table: QTable,
+    /// The game the policy was trained for, None until it is trained or loaded
+    header: Option<QTableHeader>,
+}
+
+impl QPolicy {
+    /// A policy that hasn't learned anything
+    pub fn new() -> Self {
+        QPolicy {
+            table: HashMap::new(),
+            header: None,
+        }
+    }
+
+    /// A policy playing from an existing table of positions from the view of the side to move
+    pub fn from_table(table: QTable, header: QTableHeader) -> Self {
+        QPolicy {
+            table,
+            header: Some(header),
+        }
+    }
+
+    /// The game the policy was trained for, if it has been trained
+    pub fn header(&self) -> Option<&QTableHeader> {
+        self.header.as_ref()
+    }
+
+    pub fn table(&self) -> &QTable {
+        &self.table
+    }
+
+    /// The number of positions with learned values
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Whether any pocket of the position has a learned value
+    pub fn knows(&self, game: &Game) -> bool {
+        self.table.contains_key(&view(game, game.board.player_turn))
+    }
+
+    /// The learned value of playing `pocket` for the side to move
+    pub fn value(&self, game: &Game, pocket: PocketIndex) -> Option<f64> {
+        self.table
+            .get(&view(game, game.board.player_turn))?
+            .get(&pocket)
+            .copied()
+    }
+
+    /// The pocket to play next: the best learned one, or the first playable pocket in positions
+    /// the policy hasn't learned. None if the game is over.
+    pub fn choose_move(&self, game: &Game) -> Option<PocketIndex> {
+        let state = view(game, game.board.player_turn);
+        best_pocket(&self.table, &state).or_else(|| state.actions().first().copied())
+    }
+
+    /// Every pocket to play this turn, following free turns until the turn passes.
+    /// Returns `Error::NoMoves` if the game is over.
+    pub fn choose_turn(&self, game: &Game) -> Result<Vec<PocketIndex>, Error> {
+        let side = game.board.player_turn;
+        let mut game = *game;
+        let mut turn = Vec::new();
+        while game.game_state == GameState::InProgress && game.board.player_turn == side {
+            let Some(pocket) = self.choose_move(&game) else {
+                break;
+            };
+            if game.play_move((pocket, side)).is_err() {
+                break;
+            }
+            turn.push(pocket);
+        }
+        match turn.is_empty() {
+            true => Err(Error::NoMoves),
+            false => Ok(turn),
+        }
+    }
+
+    /// Learn from `config.episodes` self-play games from `start`, continuing from what the policy
+    /// already knows. If it was trained for a different game (see `QTableHeader`), it starts over.
+    pub fn train(&mut self, start: Game, config: &QLearningConfig) {
+        let header = QTableHeader::for_game(&start);
+        if self.header != Some(header) {
+            self.table.clear();
+            self.header = Some(header);
+        }
+        let mut trainer = AgentTrainer::new();
+        trainer.import_state(std::mem::take(&mut self.table));
+        let learning = QLearning::new(config.learning_rate, config.discount, 0.0);
+        let mut rng = Rng(config.seed);
+        let mut opponent = trainer.export_learned_values();
+        for episode in 0..config.episodes {
+            if episode > 0 && episode % config.snapshot_interval.max(1) == 0 {
+                opponent = trainer.export_learned_values();
+            }
+            let side = match episode % 2 {
+                0 => PlayerSide::Player,
+                _ => PlayerSide::Opponent,
+            };
+            let mut agent = TrainingAgent::new(start, side, &opponent, rng.next_u64());
+            let mut exploration = EpsilonGreedy {
+                epsilon: config.exploration,
+                rng: Rng(rng.next_u64()),
+            };
+            // the episode ends once the learner has nothing to play, which is already the case
+            // if the opponent finished the game before the learner's first move
+            for _ in 0..MAX_EPISODE_MOVES {
+                let state = *agent.current_state();
+                if state.actions().is_empty() {
+                    break;
+                }
+                let pocket = exploration.choose(trainer.learned_values(), &state);
+                let step = StepReward {
+                    learning: &learning,
+                    start: potential(&state),
+                };
+                trainer.train(&mut agent, &step, &mut SingleStep, &ChosenPocket(pocket));
+            }
+        }
+        self.table = trainer.learned_values().clone();
+    }
+
+    /// The policy in the saved table format described at the top of this module. Positions it
+    /// can't play from (finished games or the Opponent to move) are left out.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, QTableError> {
+        let header = self.header.ok_or(QTableError::Untrained)?;
+        let variant = header
+            .rules
+            .base_variant()
+            .and_then(|variant| Variant::ALL.iter().position(|v| *v == variant))
+            .ok_or(QTableError::CustomRules)?;
+        let mut positions = self
+            .table
+            .iter()
+            .filter(|(game, values)| {
+                game.game_state == GameState::InProgress
+                    && game.board.player_turn == PlayerSide::Player
+                    && game.rules == header.rules
+                    && game.board.pockets() == header.pockets
+                    && !values.is_empty()
+            })
+            .collect::<Vec<_>>();
+        // sorted so that the same table is always saved the same way
+        positions.sort_by_key(|(game, _)| (game.board.player_pockets, game.board.opponent_pockets));
+        let mut bytes = Vec::new();
+        bytes.extend(QTABLE_MAGIC);
+        bytes.push(QTABLE_VERSION);
+        bytes.push(variant as u8);
+        bytes.push(match header.rules.sweep {
+            Sweep::None => 0,
+            Sweep::ToOwner => 1,
+        });
+        bytes.push(header.pockets as u8);
+        bytes.extend((header.stones as u32).to_le_bytes());
+        bytes.extend((positions.len() as u64).to_le_bytes());
+        for (game, values) in positions {
+            for side in [PlayerSide::Player, PlayerSide::Opponent] {
+                for pocket in 0..=header.pockets {
+                    write_varint(&mut bytes, game.board.get_stones((pocket, side)) as u32);
+                }
+            }
+            let mut values = values.iter().collect::<Vec<_>>();
+            values.sort_by_key(|(pocket, _)| **pocket);
+            bytes.push(values.len() as u8);
+            for (pocket, value) in values {
+                bytes.push(*pocket as u8);
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Reads a policy saved with `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, QTableError> {
+        let mut reader = TableReader { bytes };
+        if reader.take::<4>().map_err(|_| QTableError::NotAQTable)? != QTABLE_MAGIC {
+            return Err(QTableError::NotAQTable);
+        }
+        let version = reader.u8()?;
+        if version > QTABLE_VERSION {
+            return Err(QTableError::UnsupportedVersion(version));
+        }
+        let variant = reader.u8()?;
+        let mut rules = Variant::ALL
+            .get(variant as usize)
+            .ok_or(QTableError::UnknownVariant(variant))?
+            .rules();
+        rules.sweep = match reader.u8()? {
+            0 => Sweep::None,
+            1 => Sweep::ToOwner,
+            sweep => return Err(QTableError::UnknownSweep(sweep)),
+        };
+        let pockets = reader.u8()? as usize;
+        if !(1..=MAX_POCKETS).contains(&pockets) {
+            return Err(QTableError::InvalidPockets(pockets));
+        }
+        let stones = u32::from_le_bytes(reader.take()?) as i32;
+        let positions = u64::from_le_bytes(reader.take()?);
+        let mut table = QTable::new();
+        for _ in 0..positions {
+            let mut sides = [vec![0; pockets + 1], vec![0; pockets + 1]];
+            for side in &mut sides {
+                for stones in side.iter_mut() {
+                    *stones = reader.varint()? as i32;
+                }
+            }
+            let board = Board::free_form(&sides[0], &sides[1], PlayerSide::Player)
+                .map_err(|_| QTableError::InvalidPosition)?;
+            let mut values = HashMap::new();
+            for _ in 0..reader.u8()? {
+                let pocket = reader.u8()? as usize;
+                if pocket >= pockets {
+                    return Err(QTableError::InvalidPocket(pocket));
+                }
+                values.insert(pocket, f64::from_le_bytes(reader.take()?));
+            }
+            table.insert(Game::with_rules(board, rules), values);
+        }
+        if !reader.bytes.is_empty() {
+            return Err(QTableError::TrailingData);
+        }
+        let header = QTableHeader {
+            rules,
+            pockets,
+            stones,
+        };
+        Ok(QPolicy::from_table(table, header))
+    }
+
+    /// Saves the policy to a file, see `to_bytes`
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        std::fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// Loads a policy saved with `save`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(QPolicy::from_bytes(&std::fs::read(path)?)?)
     }
 }
@@ -0,0 +1,372 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::game::{
+    Board, Game, GameOver, GameState, InvalidPocketError, PlayerSide, PocketIndex, PocketLocation,
+    TraceStep, Winner,
+};
+use crate::position::ParsePositionError;
+use crate::rules::{Sweep, Variant};
+
+/// The name of a pocket as written in the README, e.g. `p0` for the Player's first pocket or `o6`
+/// for the Opponent's store on the default board
+pub fn pocket_notation(pocket: PocketLocation) -> String {
+    match pocket.1 {
+        PlayerSide::Player => format!("p{}", pocket.0),
+        PlayerSide::Opponent => format!("o{}", pocket.0),
+    }
+}
+
+/// A move in a game record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordEntry {
+    /// The pocket that was played
+    pub pocket: PocketLocation,
+    /// The game after the move was played
+    pub game: Game,
+}
+
+/// A game along with every move played in it, which can be stepped back and forth through.
+///
+/// Each ply is a single pocket played, so a turn with free moves spans several plies.
+/// Playing a move after stepping back discards the moves that came after it.
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    /// The game before any moves were played
+    start: Game,
+    /// Every move played, including the ones that were undone and can be redone
+    entries: Vec<RecordEntry>,
+    /// Number of moves currently applied
+    ply: usize,
+    /// Name of the person playing the Player side, if known
+    pub player_name: Option<String>,
+    /// Name of the person playing the Opponent side, if known
+    pub opponent_name: Option<String>,
+}
+
+impl GameRecord {
+    pub fn new(start: Game) -> Self {
+        GameRecord {
+            start,
+            entries: Vec::new(),
+            ply: 0,
+            player_name: None,
+            opponent_name: None,
+        }
+    }
+
+    /// The game before any moves were played
+    pub fn start(&self) -> &Game {
+        &self.start
+    }
+
+    /// The game after the moves up to the current ply
+    pub fn current(&self) -> &Game {
+        match self.ply {
+            0 => &self.start,
+            ply => &self.entries[ply - 1].game,
+        }
+    }
+
+    /// Number of moves currently applied
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    /// Every move in the record, including the ones that were undone
+    pub fn entries(&self) -> &[RecordEntry] {
+        &self.entries
+    }
+
+    /// Play a pocket for the player to move, discarding any moves that were undone
+    pub fn play_move(&mut self, pocket: PocketIndex) -> Result<(), InvalidPocketError> {
+        let mut game = *self.current();
+        let pocket = (pocket, game.board.player_turn);
+        game.play_move(pocket)?;
+        self.push(pocket, game);
+        Ok(())
+    }
+
+    /// Play a pocket like `play_move`, also returning the steps of the move
+    pub fn play_move_traced(
+        &mut self,
+        pocket: PocketIndex,
+    ) -> Result<Vec<TraceStep>, InvalidPocketError> {
+        let mut game = *self.current();
+        let pocket = (pocket, game.board.player_turn);
+        let steps = game.play_move_traced(pocket)?;
+        self.push(pocket, game);
+        Ok(steps)
+    }
+
+    /// Add a move after the current ply, discarding any moves that were undone
+    fn push(&mut self, pocket: PocketLocation, game: Game) {
+        self.entries.truncate(self.ply);
+        self.entries.push(RecordEntry { pocket, game });
+        self.ply += 1;
+    }
+
+    /// Step back one move. Returns false if there are no moves to undo
+    pub fn undo(&mut self) -> bool {
+        if self.ply == 0 {
+            return false;
+        }
+        self.ply -= 1;
+        true
+    }
+
+    /// Step forward one undone move. Returns false if there are no moves to redo
+    pub fn redo(&mut self) -> bool {
+        if self.ply == self.entries.len() {
+            return false;
+        }
+        self.ply += 1;
+        true
+    }
+
+    /// Jump to the game after `ply` moves. Returns false if the record doesn't have that many moves
+    pub fn jump_to(&mut self, ply: usize) -> bool {
+        if ply > self.entries.len() {
+            return false;
+        }
+        self.ply = ply;
+        true
+    }
+}
+
+/*
+Records are written in a line oriented text format, similar to PGN for chess.
+The header comes first, one tag per line, followed by the moves of the game up to the current ply.
+Each line of moves is one turn, numbered from 1, with the pockets written as in the README.
+[Variant "Avalanche"]
+[Sweep "No"]
+[Player "?"]
+[Opponent "?"]
+[Board "4,4,4,4,4,4/0 4,4,4,4,4,4/0 P"]
+[Result "*"]
+
+1. p2 p5
+2. o0
+The board is the starting position, written in the position notation (see position.rs), so it
+includes whose turn it is. Unknown names are written as "?", and the result is one of "Player",
+"Opponent", "Tie" or "*" for a game that is not over. It has to match the result of the moves.
+Lines starting with ';' are comments.
+*/
+
+/// Error type for reading a game record
+#[derive(Debug)]
+pub enum ParseRecordError {
+    /// A header line is not of the form [Tag "value"]
+    MalformedHeader(String),
+    /// A required tag is missing from the header
+    MissingTag(&'static str),
+    /// A tag has a value that can't be understood
+    InvalidTag(&'static str, String),
+    /// The Board tag isn't a position that can be played on
+    InvalidBoard(ParsePositionError),
+    /// A move is not a pocket like p2 or o5
+    MalformedMove(String),
+    /// A move can't be played in the game at that point
+    IllegalMove(String, InvalidPocketError),
+    /// The Result tag doesn't match the result of the moves
+    WrongResult {
+        recorded: String,
+        played: &'static str,
+    },
+}
+
+impl Display for ParseRecordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseRecordError::MalformedHeader(line) => write!(f, "malformed header line: {}", line),
+            ParseRecordError::MissingTag(tag) => write!(f, "missing {} tag", tag),
+            ParseRecordError::InvalidTag(tag, value) => {
+                write!(f, "invalid value for {} tag: {}", tag, value)
+            }
+            ParseRecordError::InvalidBoard(error) => write!(f, "invalid board: {}", error),
+            ParseRecordError::MalformedMove(token) => write!(f, "malformed move: {}", token),
+            ParseRecordError::IllegalMove(token, error) => {
+                write!(f, "illegal move {}: {}", token, error)
+            }
+            ParseRecordError::WrongResult { recorded, played } => write!(
+                f,
+                "the result is recorded as {} but the moves give {}",
+                recorded, played
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseRecordError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseRecordError::InvalidBoard(error) => Some(error),
+            ParseRecordError::IllegalMove(_, error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// The result tag for a game
+pub(crate) fn result_tag(game: &Game) -> &'static str {
+    match game.game_state {
+        GameState::InProgress => "*",
+        GameState::Over(GameOver::Win(Winner::Player))
+        | GameState::Over(GameOver::TechnicalWin(PlayerSide::Player)) => "Player",
+        GameState::Over(GameOver::Win(Winner::Opponent))
+        | GameState::Over(GameOver::TechnicalWin(PlayerSide::Opponent)) => "Opponent",
+        GameState::Over(GameOver::Win(Winner::Tie)) => "Tie",
+    }
+}
+
+fn parse_pocket(token: &str) -> Option<PocketLocation> {
+    let side = match token.chars().next()? {
+        'p' => PlayerSide::Player,
+        'o' => PlayerSide::Opponent,
+        _ => return None,
+    };
+    let pocket = token[1..].parse::<PocketIndex>().ok()?;
+    Some((pocket, side))
+}
+
+impl Display for GameRecord {
+    /// Writes the record in the text format described above.
+    /// Rules that don't match any variant are written as "Custom", which can't be read back
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let variant = match self.start.rules.base_variant() {
+            Some(variant) => variant.to_string(),
+            None => "Custom".to_string(),
+        };
+        let sweep = match self.start.rules.sweep {
+            Sweep::ToOwner => "Yes",
+            Sweep::None => "No",
+        };
+        writeln!(f, "[Variant \"{}\"]", variant)?;
+        writeln!(f, "[Sweep \"{}\"]", sweep)?;
+        writeln!(
+            f,
+            "[Player \"{}\"]",
+            self.player_name.as_deref().unwrap_or("?")
+        )?;
+        writeln!(
+            f,
+            "[Opponent \"{}\"]",
+            self.opponent_name.as_deref().unwrap_or("?")
+        )?;
+        writeln!(f, "[Board \"{}\"]", self.start.board)?;
+        writeln!(f, "[Result \"{}\"]", result_tag(self.current()))?;
+        writeln!(f)?;
+        let mut turn = 0;
+        let mut previous_side = None;
+        for entry in &self.entries[..self.ply] {
+            if previous_side != Some(entry.pocket.1) {
+                if turn > 0 {
+                    writeln!(f)?;
+                }
+                turn += 1;
+                write!(f, "{}.", turn)?;
+                previous_side = Some(entry.pocket.1);
+            }
+            write!(f, " {}", pocket_notation(entry.pocket))?;
+        }
+        if turn > 0 {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = ParseRecordError;
+
+    /// Reads a record in the text format described above, replaying its moves
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tags: Vec<(String, String)> = Vec::new();
+        let mut move_tokens = Vec::new();
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') {
+                let malformed = || ParseRecordError::MalformedHeader(line.to_string());
+                let inner = line
+                    .strip_prefix('[')
+                    .and_then(|line| line.strip_suffix(']'))
+                    .ok_or_else(malformed)?;
+                let (tag, value) = inner.split_once(' ').ok_or_else(malformed)?;
+                let value = value
+                    .trim()
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .ok_or_else(malformed)?;
+                tags.push((tag.to_string(), value.to_string()));
+                continue;
+            }
+            // turn numbers like "12." are only there for reading
+            move_tokens.extend(
+                line.split_whitespace()
+                    .filter(|token| !token.ends_with('.'))
+                    .map(str::to_string),
+            );
+        }
+        let tag = |name: &'static str| {
+            tags.iter()
+                .find(|(tag, _)| tag == name)
+                .map(|(_, value)| value.as_str())
+                .ok_or(ParseRecordError::MissingTag(name))
+        };
+        let invalid =
+            |name: &'static str, value: &str| ParseRecordError::InvalidTag(name, value.to_string());
+
+        let variant = tag("Variant")?;
+        let mut rules = Variant::ALL
+            .into_iter()
+            .find(|known| known.to_string() == variant)
+            .ok_or_else(|| invalid("Variant", variant))?
+            .rules();
+        rules.sweep = match tag("Sweep")? {
+            "Yes" => Sweep::ToOwner,
+            "No" => Sweep::None,
+            sweep => return Err(invalid("Sweep", sweep)),
+        };
+        let board = tag("Board")?
+            .parse::<Board>()
+            .map_err(ParseRecordError::InvalidBoard)?;
+        let result = tag("Result")?;
+        if !["Player", "Opponent", "Tie", "*"].contains(&result) {
+            return Err(invalid("Result", result));
+        }
+        let name = |name: &'static str| -> Result<Option<String>, ParseRecordError> {
+            Ok(match tag(name)? {
+                "?" => None,
+                value => Some(value.to_string()),
+            })
+        };
+
+        let start = Game::with_rules(board, rules);
+        let mut record = GameRecord::new(start);
+        record.player_name = name("Player")?;
+        record.opponent_name = name("Opponent")?;
+        for token in move_tokens {
+            let pocket = parse_pocket(&token)
+                .ok_or_else(|| ParseRecordError::MalformedMove(token.clone()))?;
+            if pocket.1 != record.current().board.player_turn {
+                return Err(ParseRecordError::IllegalMove(
+                    token,
+                    InvalidPocketError::WrongPlayer,
+                ));
+            }
+            record
+                .play_move(pocket.0)
+                .map_err(|error| ParseRecordError::IllegalMove(token.clone(), error))?;
+        }
+        let played = result_tag(record.current());
+        if result != played {
+            return Err(ParseRecordError::WrongResult {
+                recorded: result.to_string(),
+                played,
+            });
+        }
+        Ok(record)
+    }
+}
@@ -0,0 +1,35 @@
+/// One step of the splitmix64 generator. It is a `const fn` so the Zobrist key tables can be
+/// filled at compile time.
+/// Returns the next state and the generated number.
+pub(crate) const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (state, z ^ (z >> 31))
+}
+
+/// Small splitmix64 based generator for the engine's random choices (self-play openings, Monte
+/// Carlo playouts, random players and Q-learning exploration), seeded so that results can be
+/// reproduced
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        let (state, value) = splitmix64(self.0);
+        self.0 = state;
+        value
+    }
+
+    /// A random number in 0..n
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// A random number in [0, 1)
+    #[cfg(feature = "qlearning")]
+    pub(crate) fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt::Display;
+
+/// How the stones picked up from a pocket are dropped
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Sowing {
+    /// One stone in each side pocket and the mover's store, skipping the opponent's store
+    Store,
+    /// One stone in each side pocket, skipping both stores and the pocket the stones came from (Oware)
+    SkipStores,
+}
+
+/// What happens when the last stone of a move doesn't start another pickup
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Capture {
+    /// Nothing is captured
+    None,
+    /// The last stone landing in an empty pocket on the mover's side captures itself and the
+    /// stones in the opposite pocket, if there are any (classic Kalah)
+    EmptyPocket,
+    /// The last stone landing on the opponent's side and making 2 or 3 stones captures that pocket,
+    /// along with each pocket before it on the opponent's side that also holds 2 or 3 (Oware)
+    TwoOrThree,
+}
+
+/// When the game is over
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GameEnd {
+    /// As soon as either side has no stones left in its side pockets
+    EitherSideEmpty,
+    /// When the player whose turn it is has no stones left to play
+    MoverSideEmpty,
+}
+
+/// What happens to the stones left in the side pockets when the game ends
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Sweep {
+    /// They stay where they are and don't count towards either score (Game Pigeon)
+    None,
+    /// Each side's remaining stones are moved into that side's store
+    ToOwner,
+}
+
+/// The rules a game is played with
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rules {
+    pub sowing: Sowing,
+    pub capture: Capture,
+    /// Whether the last stone landing in a non-empty side pocket picks up that pocket and keeps going
+    pub avalanche: bool,
+    /// Whether the last stone landing in the mover's store gives them another turn
+    pub free_turn: bool,
+    pub game_end: GameEnd,
+    pub sweep: Sweep,
+}
+
+/// Named sets of rules
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Variant {
+    /// The iMessage Game Pigeon avalanche version
+    Avalanche,
+    /// Classic Kalah with captures
+    Kalah,
+    /// Oware style sowing and captures
+    Oware,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 3] = [Variant::Avalanche, Variant::Kalah, Variant::Oware];
+
+    pub fn rules(self) -> Rules {
+        match self {
+            Variant::Avalanche => Rules {
+                sowing: Sowing::Store,
+                capture: Capture::None,
+                avalanche: true,
+                free_turn: true,
+                game_end: GameEnd::EitherSideEmpty,
+                sweep: Sweep::None,
+            },
+            Variant::Kalah => Rules {
+                sowing: Sowing::Store,
+                capture: Capture::EmptyPocket,
+                avalanche: false,
+                free_turn: true,
+                game_end: GameEnd::EitherSideEmpty,
+                sweep: Sweep::ToOwner,
+            },
+            Variant::Oware => Rules {
+                sowing: Sowing::SkipStores,
+                capture: Capture::TwoOrThree,
+                avalanche: false,
+                free_turn: false,
+                game_end: GameEnd::MoverSideEmpty,
+                sweep: Sweep::ToOwner,
+            },
+        }
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Variant::Avalanche => write!(f, "Avalanche"),
+            Variant::Kalah => write!(f, "Kalah"),
+            Variant::Oware => write!(f, "Oware"),
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Variant::Avalanche.rules()
+    }
+}
+
+impl From<Variant> for Rules {
+    fn from(variant: Variant) -> Self {
+        variant.rules()
+    }
+}
+
+impl Rules {
+    /// The variant these rules match, if any
+    pub fn variant(&self) -> Option<Variant> {
+        Variant::ALL
+            .into_iter()
+            .find(|variant| variant.rules() == *self)
+    }
+
+    /// The variant these rules match apart from the sweep, if any
+    pub fn base_variant(&self) -> Option<Variant> {
+        Variant::ALL.into_iter().find(|variant| {
+            let mut variant_rules = variant.rules();
+            variant_rules.sweep = self.sweep;
+            variant_rules == *self
+        })
+    }
+}
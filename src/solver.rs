@@ -1,4 +1,10 @@
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::error::Error;
+use crate::eval::Evaluator;
 use crate::game::{Game, GameState, PlayerSide, PocketIndex};
+use crate::transposition::TranspositionTable;
 
 pub type SequenceTreeIndex = usize;
 
@@ -17,26 +23,36 @@ struct SequenceTree { // The tree itself
                     },
                 ), // A node that represents a move
             },
+            children: Range<usize>, // The indices of the children, which are always next to each other
         },
     >,
     leaf_nodes: Vec<usize>,
     game_over_nodes: Vec<usize>,
 }
+Nodes don't store their path from the root or their depth; both are found by following the parent
+links up to the root, which only happens once per sequence that is actually played or displayed.
 */
 
 /// A move that can be made in the game
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Move {
     /// The pocket that the move is made from
     pocket: PocketIndex,
-    /// Resulting score of the move
-    pub score: i32,
-    /// Whether or not the player gets a free turn
-    free_turn: bool,
     /// The resulting game state after the move is made
     pub game: Game,
 }
 
+/// A turn found by `SequenceTree::get_best_sequences`
+#[derive(Debug, Clone)]
+pub struct RankedSequence {
+    /// The pockets played during the turn, in order
+    pub sequence: Vec<PocketIndex>,
+    /// The evaluation of the game at the end of the turn
+    pub evaluation: f32,
+    /// The game at the end of the turn
+    pub game: Game,
+}
+
 /// Tree containing all possible sequences of moves for a given turn
 pub struct SequenceTree {
     /// The nodes in the tree
@@ -46,9 +62,12 @@ pub struct SequenceTree {
     /// The indices of the game over nodes in the nodes vector; used to find game ending
     /// sequences, is a subset of leaf nodes
     pub game_over_nodes: Vec<SequenceTreeIndex>,
+    /// The indices of nodes that were not expanded because their position was already expanded
+    /// elsewhere in the tree (only filled when generating with a transposition table)
+    pub transposition_nodes: Vec<SequenceTreeIndex>,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// A variant of SequenceNodeEnum that represents a move node
 pub struct MoveNode {
     /// The move that this node represents
@@ -57,7 +76,7 @@ pub struct MoveNode {
     parent: SequenceTreeIndex,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// An enum that represents the different types of nodes in the sequence tree
 pub enum SequenceNodeEnum {
     /// The root node which contains the initial game state
@@ -66,33 +85,36 @@ pub enum SequenceNodeEnum {
     Move(MoveNode),
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// A node in the sequence tree
 pub struct SequenceNode {
     /// The data contained in the node (either a root node or a move node)
     pub node_enum: SequenceNodeEnum,
-    /// Indices of the children of the node in the nodes vector
-    children: Vec<SequenceTreeIndex>,
-    /// Depth of the node in the tree (calculated from the root node)
-    depth: usize,
-    /// Path to the node from the root node (usize indices of the nodes in the nodes vector)
-    pub path: Vec<SequenceTreeIndex>,
+    /// Indices of the children of the node in the nodes vector. Children are created together,
+    /// so they are always next to each other
+    children: Range<SequenceTreeIndex>,
+}
+
+/// How much memory a sequence tree uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeSize {
+    /// The number of nodes in the tree, including the root
+    pub nodes: usize,
+    /// The bytes allocated for the tree, including unused capacity of its vectors
+    pub bytes: usize,
 }
 
 impl Game {
     fn possible_moves(&self) -> Vec<Move> {
         let mut moves = Vec::new();
-        for pocket in 0..6 {
-            if self.board.get_stones((pocket, self.board.player_turn)) == 0 {
+        for pocket in 0..self.board.pockets() {
+            let mut game = *self; // May need to be cloned
+                                  // empty pockets can't be played
+            if game.play_move((pocket, self.board.player_turn)).is_err() {
                 continue;
             }
-            let mut game = *self; // May need to be cloned
-            game.play_move((pocket, self.board.player_turn))
-                .expect("Invalid move");
             moves.push(Move {
                 pocket,
-                score: game.board.get_stones((6, self.board.player_turn)),
-                free_turn: game.board.player_turn == self.board.player_turn,
                 game, // May need to be cloned
             });
         }
@@ -104,47 +126,44 @@ impl SequenceTree {
     pub fn new(game: Game) -> Self {
         let root = SequenceNode {
             node_enum: SequenceNodeEnum::Root(game),
-            children: Vec::new(),
-            depth: 0,
-            path: Vec::new(),
+            children: 0..0,
         };
         SequenceTree {
             nodes: vec![root],
             leaf_nodes: vec![],
             game_over_nodes: vec![],
+            transposition_nodes: vec![],
         }
     }
 
-    /// for each move, create a new node, push it to the nodes vector, and add the index to the parent's children vector
-    fn create_children(&mut self, moves: Vec<Move>, parent_index: SequenceTreeIndex) {
+    /// for each move, create a new node, push it to the nodes vector, and set the parent's children to the new nodes
+    fn create_children(
+        &mut self,
+        moves: Vec<Move>,
+        parent_index: SequenceTreeIndex,
+        player_turn: PlayerSide,
+    ) {
+        let first_child = self.nodes.len();
         for r#move in moves {
             let node = SequenceNode {
                 node_enum: SequenceNodeEnum::Move(MoveNode {
                     r#move,
                     parent: parent_index,
                 }),
-                children: Vec::new(),
-                depth: match self.nodes[parent_index].node_enum {
-                    SequenceNodeEnum::Root(_) => 1,
-                    SequenceNodeEnum::Move(ref move_node) => self.nodes[move_node.parent].depth + 1,
-                },
-                path: {
-                    let mut path = self.nodes[parent_index].path.clone();
-                    path.push(parent_index);
-                    path
-                },
+                children: 0..0,
             };
             self.nodes.push(node);
             let child_index = self.nodes.len() - 1;
-            self.nodes[parent_index].children.push(child_index);
+            let free_turn = r#move.game.board.player_turn == player_turn;
             // if the turn is over or the game is over (even by technicality), add the index to the leaf_nodes vector
-            if !r#move.free_turn || r#move.game.game_state != GameState::InProgress {
+            if !free_turn || r#move.game.game_state != GameState::InProgress {
                 self.leaf_nodes.push(child_index);
             }
             if r#move.game.game_state != GameState::InProgress {
                 self.game_over_nodes.push(child_index);
             }
         }
+        self.nodes[parent_index].children = first_child..self.nodes.len();
     }
 
     /// Recursively generate the sequence tree
@@ -153,7 +172,109 @@ impl SequenceTree {
         player_turn: PlayerSide,
         parent_index: Option<SequenceTreeIndex>,
     ) {
-        let parent_index = parent_index.unwrap_or(0);
+        self.expand(player_turn, parent_index.unwrap_or(0), None);
+        self.shrink_to_fit();
+    }
+
+    /// Generate the same tree as `generate_tree` from the root, building the subtree of each of the
+    /// root's moves on its own thread and then joining them in order. Without the `parallel`
+    /// feature the subtrees are built one after another.
+    pub fn generate_tree_parallel(&mut self, player_turn: PlayerSide) {
+        let game = self.node_game(0);
+        if game.game_state != GameState::InProgress || game.board.player_turn != player_turn {
+            return;
+        }
+        self.create_children(game.possible_moves(), 0, player_turn);
+        let children = self.nodes[0].children.clone();
+        let games = children
+            .clone()
+            .map(|child_index| self.node_game(child_index))
+            .collect::<Vec<Game>>();
+        let build_subtree = |game: Game| {
+            let mut subtree = SequenceTree::new(game);
+            subtree.expand(player_turn, 0, None);
+            subtree
+        };
+        #[cfg(feature = "parallel")]
+        let subtrees = {
+            use rayon::prelude::*;
+            games
+                .into_par_iter()
+                .map(build_subtree)
+                .collect::<Vec<SequenceTree>>()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let subtrees = games
+            .into_iter()
+            .map(build_subtree)
+            .collect::<Vec<SequenceTree>>();
+        let subtree_nodes = subtrees.iter().map(|subtree| subtree.nodes.len() - 1);
+        self.nodes.reserve(subtree_nodes.sum());
+        for (child_index, subtree) in children.zip(subtrees) {
+            self.graft(child_index, subtree);
+        }
+        self.shrink_to_fit();
+    }
+
+    /// Appends the nodes of a subtree generated from the game at `index`, which is the subtree's
+    /// root. The sequential generator expands each child completely before the next one, so
+    /// grafting the subtrees in order gives the nodes the same indices it would.
+    fn graft(&mut self, index: SequenceTreeIndex, subtree: SequenceTree) {
+        // subtree node i (other than the root) becomes node offset + i
+        let offset = self.nodes.len() - 1;
+        let remap = |i: SequenceTreeIndex| match i {
+            0 => index,
+            i => offset + i,
+        };
+        // unexpanded nodes keep their empty 0..0 range, just like in the sequential tree
+        let remap_children = |children: Range<SequenceTreeIndex>| match children == (0..0) {
+            true => 0..0,
+            false => children.start + offset..children.end + offset,
+        };
+        let mut nodes = subtree.nodes.into_iter();
+        if let Some(root) = nodes.next() {
+            self.nodes[index].children = remap_children(root.children);
+        }
+        for mut node in nodes {
+            if let SequenceNodeEnum::Move(ref mut move_node) = node.node_enum {
+                move_node.parent = remap(move_node.parent);
+            }
+            node.children = remap_children(node.children);
+            self.nodes.push(node);
+        }
+        self.leaf_nodes
+            .extend(subtree.leaf_nodes.into_iter().map(remap));
+        self.game_over_nodes
+            .extend(subtree.game_over_nodes.into_iter().map(remap));
+    }
+
+    /// The game after the move at the given index, or the starting game for the root
+    fn node_game(&self, index: SequenceTreeIndex) -> Game {
+        match self.nodes[index].node_enum {
+            SequenceNodeEnum::Root(game) => game,
+            SequenceNodeEnum::Move(ref move_node) => move_node.r#move.game,
+        }
+    }
+
+    /// Generate the sequence tree, skipping positions that were already expanded in this tree.
+    /// Free moves played in a different order often reach the same position, whose continuations
+    /// only need to be generated once. Skipped nodes are recorded in `transposition_nodes`.
+    pub fn generate_tree_with_table(
+        &mut self,
+        player_turn: PlayerSide,
+        table: &mut TranspositionTable,
+    ) {
+        table.new_search();
+        self.expand(player_turn, 0, Some(table));
+        self.shrink_to_fit();
+    }
+
+    fn expand(
+        &mut self,
+        player_turn: PlayerSide,
+        parent_index: SequenceTreeIndex,
+        mut table: Option<&mut TranspositionTable>,
+    ) {
         let game = match self.nodes[parent_index].node_enum {
             SequenceNodeEnum::Root(ref game) => *game, // May need to be cloned
             SequenceNodeEnum::Move(ref move_node) => move_node.r#move.game, // May need to be cloned
@@ -162,48 +283,88 @@ impl SequenceTree {
         if game.game_state != GameState::InProgress || game.board.player_turn != player_turn {
             return;
         }
+        // Markers from earlier searches belong to other trees, whose nodes can't be reused here
+        if let Some(ref mut table) = table {
+            if table.mark_expanded(&game) {
+                self.transposition_nodes.push(parent_index);
+                return;
+            }
+        }
         let moves = game.possible_moves();
-        self.create_children(moves, parent_index);
-        // recursively generate the tree for each new child=
-        self.nodes[parent_index]
-            .children
-            .clone()
-            .iter()
-            .for_each(|child_index| {
-                let child = &self.nodes[*child_index];
-                match child.node_enum {
-                    SequenceNodeEnum::Move(_) => {
-                        self.generate_tree(player_turn, Some(*child_index));
-                    }
-                    // This realistically should never happen since each child created will always bs the Move variant
-                    _ => panic!("Child node is not a move node"),
-                }
-            });
+        self.create_children(moves, parent_index, player_turn);
+        // recursively generate the tree for each new child, which are always move nodes
+        for child_index in self.nodes[parent_index].children.clone() {
+            self.expand(player_turn, child_index, table.as_deref_mut());
+        }
     }
 
-    pub fn get_move_sequence(&self, node_index: SequenceTreeIndex) -> Vec<PocketIndex> {
+    /// The pockets played from the root to reach the node at the given index.
+    /// The sequence of the root node is empty.
+    pub fn get_move_sequence(
+        &self,
+        node_index: SequenceTreeIndex,
+    ) -> Result<Vec<PocketIndex>, Error> {
+        let mut node = self
+            .nodes
+            .get(node_index)
+            .ok_or(Error::InvalidNode(node_index))?;
         let mut move_sequence = Vec::new();
-        for index in &self.nodes[node_index].path {
-            if let SequenceNodeEnum::Move(ref move_node) = self.nodes[*index].node_enum {
-                move_sequence.push(move_node.r#move.pocket)
+        // follow the parent links up to the root, which gives the moves in reverse
+        while let SequenceNodeEnum::Move(ref move_node) = node.node_enum {
+            move_sequence.push(move_node.r#move.pocket);
+            node = &self.nodes[move_node.parent];
+        }
+        move_sequence.reverse();
+        Ok(move_sequence)
+    }
+
+    /// Frees the capacity the vectors grew past while the tree was generated
+    fn shrink_to_fit(&mut self) {
+        self.nodes.shrink_to_fit();
+        self.leaf_nodes.shrink_to_fit();
+        self.game_over_nodes.shrink_to_fit();
+        self.transposition_nodes.shrink_to_fit();
+    }
+
+    /// The number of nodes in the tree and the bytes allocated for it
+    pub fn size(&self) -> TreeSize {
+        let index_bytes = std::mem::size_of::<SequenceTreeIndex>()
+            * (self.leaf_nodes.capacity()
+                + self.game_over_nodes.capacity()
+                + self.transposition_nodes.capacity());
+        TreeSize {
+            nodes: self.nodes.len(),
+            bytes: std::mem::size_of::<SequenceTree>()
+                + std::mem::size_of::<SequenceNode>() * self.nodes.capacity()
+                + index_bytes,
+        }
+    }
+
+    /// The leaves to choose a turn from: only the game over nodes if prefer_win is set and there
+    /// are any, otherwise every leaf
+    fn candidate_leaves(&self, prefer_win: bool) -> &[SequenceTreeIndex] {
+        match prefer_win {
+            true => {
+                if self.game_over_nodes.is_empty() {
+                    &self.leaf_nodes
+                } else {
+                    &self.game_over_nodes
+                }
             }
+            false => &self.leaf_nodes,
         }
-        move_sequence.push(match self.nodes[node_index].node_enum {
-            SequenceNodeEnum::Move(ref move_node) => move_node.r#move.pocket,
-            _ => panic!("Leaf node is not a move node"),
-        });
-        move_sequence
     }
 
-    pub fn get_best_sequence(
+    /// The turn whose final position `evaluator` scores best for `player_side`
+    pub fn get_best_sequence<E: Evaluator + ?Sized>(
         &self,
-        eval_method: &EvalMethod,
+        evaluator: &E,
         // If prefer_win is true, will only search game over nodes if available, otherwise, will search all leaf nodes.
         // Generally this should always be set to true except for testing purposes.
         prefer_win: bool,
         player_side: &PlayerSide,
-    ) -> Vec<PocketIndex> {
-        let mut best_move_index = 0;
+    ) -> Result<Vec<PocketIndex>, Error> {
+        let mut best_move_index = None;
         let mut best_evaluation = match player_side {
             PlayerSide::Player => f32::NEG_INFINITY,
             PlayerSide::Opponent => f32::INFINITY,
@@ -212,43 +373,61 @@ impl SequenceTree {
             PlayerSide::Player => f32::gt,
             PlayerSide::Opponent => f32::lt,
         };
-        let filtered_leaf_nodes = match prefer_win {
-            true => {
-                if self.game_over_nodes.is_empty() {
-                    &self.leaf_nodes
-                } else {
-                    &self.game_over_nodes
-                }
-            }
-            false => &self.leaf_nodes,
-        };
-        for index in filtered_leaf_nodes {
+        for index in self.candidate_leaves(prefer_win) {
             let game = match self.nodes[*index].node_enum {
                 SequenceNodeEnum::Move(ref move_node) => move_node.r#move.game,
-                _ => panic!("Leaf node is not a move node"),
+                SequenceNodeEnum::Root(_) => continue,
             };
-            let evaluation = evaluate(&game, eval_method);
+            let evaluation = evaluator.evaluate(&game);
             if comparison(&evaluation, &best_evaluation) {
                 best_evaluation = evaluation;
-                best_move_index = *index;
+                best_move_index = Some(*index);
             }
         }
-        self.get_move_sequence(best_move_index)
+        // there are no leaves if the game was already over or the tree was never generated
+        self.get_move_sequence(best_move_index.ok_or(Error::NoMoves)?)
     }
-}
-
-pub enum EvalMethod {
-    ByDifference,
-}
-
-/// Evaluate a game state by the difference in score between the two players
-fn eval_by_difference(game: &Game) -> f32 {
-    game.board.get_stones((6, PlayerSide::Player)) as f32
-        - game.board.get_stones((6, PlayerSide::Opponent)) as f32
-}
 
-pub fn evaluate(game: &Game, eval_method: &EvalMethod) -> f32 {
-    match eval_method {
-        EvalMethod::ByDifference => eval_by_difference(game),
+    /// Up to `k` turns ordered best first for `player_side`, with their evaluations and the game
+    /// at the end of each turn. Sequences that end on the same board are only listed once (the
+    /// first one found), and turns with equal evaluations keep the order they were generated in.
+    pub fn get_best_sequences<E: Evaluator + ?Sized>(
+        &self,
+        evaluator: &E,
+        k: usize,
+        prefer_win: bool,
+        player_side: &PlayerSide,
+    ) -> Result<Vec<RankedSequence>, Error> {
+        let mut seen = HashSet::new();
+        let mut ranked = Vec::new();
+        for index in self.candidate_leaves(prefer_win) {
+            let game = match self.nodes[*index].node_enum {
+                SequenceNodeEnum::Move(ref move_node) => move_node.r#move.game,
+                SequenceNodeEnum::Root(_) => continue,
+            };
+            if !seen.insert(game.board) {
+                continue;
+            }
+            ranked.push((*index, evaluator.evaluate(&game), game));
+        }
+        if ranked.is_empty() {
+            return Err(Error::NoMoves);
+        }
+        // sort_by is stable, so ties stay in the order get_best_sequence would pick them
+        match player_side {
+            PlayerSide::Player => ranked.sort_by(|a, b| b.1.total_cmp(&a.1)),
+            PlayerSide::Opponent => ranked.sort_by(|a, b| a.1.total_cmp(&b.1)),
+        }
+        ranked.truncate(k);
+        ranked
+            .into_iter()
+            .map(|(index, evaluation, game)| {
+                Ok(RankedSequence {
+                    sequence: self.get_move_sequence(index)?,
+                    evaluation,
+                    game,
+                })
+            })
+            .collect()
     }
 }
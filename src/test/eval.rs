@@ -0,0 +1,172 @@
+use crate::eval::{
+    Evaluator, Feature, FeatureWeights, FreeMovePotential, Mobility, ParseWeightsError,
+    StonesOnSide, StoreDifference, Weighted,
+};
+use crate::game::{Board, Game, GameState, PlayerSide, MAX_POCKETS};
+use crate::rules::{Rules, Sowing, Variant};
+
+use super::board;
+
+/// A position worked out by hand, with 3 pockets a side so a lap of store sowing is 7 pockets:
+/// the Player's first pocket goes round once and ends in the store, the second ends there
+/// directly, and only the Opponent's last pocket ends in their store
+fn hand_position(variant: Variant) -> Game {
+    Game::with_rules(
+        board(&[10, 2, 0, 5], &[1, 8, 1, 4], PlayerSide::Player),
+        variant.rules(),
+    )
+}
+
+#[test]
+fn evaluators_on_a_hand_checked_position() {
+    for variant in Variant::ALL {
+        let game = hand_position(variant);
+        assert_eq!(game.game_state, GameState::InProgress);
+        assert_eq!(StoreDifference.evaluate(&game), 1.0);
+        assert_eq!(StonesOnSide.evaluate(&game), 2.0);
+        assert_eq!(Mobility.evaluate(&game), -1.0);
+        let free_moves = match variant.rules().sowing {
+            Sowing::Store => 1.0,
+            // sowing never ends in a store, and Oware has no free turns anyway
+            Sowing::SkipStores => 0.0,
+        };
+        assert_eq!(FreeMovePotential.evaluate(&game), free_moves, "{}", variant);
+        // every evaluator is from the Player's point of view, whoever is to move
+        let mirrored = game.mirrored();
+        for feature in Feature::ALL {
+            assert_eq!(
+                feature.evaluate(&mirrored),
+                -feature.evaluate(&game),
+                "{}",
+                feature
+            );
+        }
+    }
+}
+
+#[test]
+fn free_move_potential_matches_sowing() {
+    let kalah = Variant::Kalah.rules();
+    let rules = [
+        kalah,
+        // without free turns, or sowing that skips the stores, a turn never continues
+        Rules {
+            free_turn: false,
+            ..kalah
+        },
+        Rules {
+            sowing: Sowing::SkipStores,
+            ..kalah
+        },
+    ];
+    for rules in rules {
+        for pockets in 1..=MAX_POCKETS {
+            // a few laps of the board, whose length depends on the number of pockets
+            for stones in 1..=3 * (2 * pockets as i32 + 1) + 1 {
+                for pocket in 0..pockets {
+                    let mut player = vec![0; pockets + 1];
+                    player[pocket] = stones;
+                    let opponent = vec![0; pockets + 1];
+                    let board = Board::free_form(&player, &opponent, PlayerSide::Player).unwrap();
+                    // built directly, since a board with an empty side is already over
+                    let game = Game {
+                        board,
+                        game_state: GameState::InProgress,
+                        rules,
+                    };
+                    let mut played = game;
+                    played.play_move((pocket, PlayerSide::Player)).unwrap();
+                    let free_turn = played.board.player_turn == PlayerSide::Player;
+                    let expected = if free_turn { 1.0 } else { 0.0 };
+                    assert_eq!(
+                        FreeMovePotential.evaluate(&game),
+                        expected,
+                        "{} stones in pocket {} of {}",
+                        stones,
+                        pocket,
+                        pockets
+                    );
+                    assert_eq!(FreeMovePotential.evaluate(&game.mirrored()), -expected);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn weighted_sums_its_evaluators() {
+    let game = hand_position(Variant::Kalah);
+    let weighted = Weighted::new()
+        .with(2.0, StoreDifference)
+        .with(-0.5, Mobility)
+        .with(3.0, |_: &Game| 1.0);
+    assert_eq!(weighted.weights(), vec![2.0, -0.5, 3.0]);
+    assert_eq!(weighted.evaluate(&game), 2.0 + 0.5 + 3.0);
+    assert_eq!(Weighted::new().evaluate(&game), 0.0);
+
+    let mut weights = FeatureWeights::zero();
+    let mut same = Weighted::new();
+    for (feature, weight) in Feature::ALL.into_iter().zip([1.0, 0.5, -0.25, 2.0]) {
+        weights.set(feature, weight);
+        same = same.with(weight, feature);
+    }
+    assert_eq!(weights.evaluate(&game), same.evaluate(&game));
+    assert_eq!(
+        FeatureWeights::features(&game),
+        Feature::ALL.map(|feature| feature.evaluate(&game))
+    );
+    assert_eq!(
+        FeatureWeights::default().evaluate(&game),
+        StoreDifference.evaluate(&game)
+    );
+}
+
+#[test]
+fn weights_file_round_trip() {
+    let mut weights = FeatureWeights::zero();
+    weights.set(Feature::StoreDifference, 1.0);
+    weights.set(Feature::Mobility, -0.125);
+    weights.set(Feature::FreeMovePotential, 2.5);
+    let text = weights.to_string();
+    assert_eq!(
+        text,
+        "StoreDifference 1\nStonesOnSide 0\nMobility -0.125\nFreeMovePotential 2.5\n"
+    );
+    assert_eq!(text.parse::<FeatureWeights>(), Ok(weights));
+    // features left out are 0, names are read case-insensitively, and comments are skipped
+    let parsed =
+        "; tuned by hand\n\n  mobility   -0.125\nFREEMOVEPOTENTIAL 2.5\nStoreDifference 1\n"
+            .parse::<FeatureWeights>();
+    assert_eq!(parsed, Ok(weights));
+    assert_eq!("".parse::<FeatureWeights>(), Ok(FeatureWeights::zero()));
+}
+
+#[test]
+fn weights_file_errors() {
+    use ParseWeightsError::*;
+    let parse = |s: &str| s.parse::<FeatureWeights>().err();
+    assert_eq!(
+        parse("StoreDifference 1\nMaterial 2"),
+        Some(UnknownFeature("Material".to_string()))
+    );
+    assert_eq!(
+        parse("Mobility 1\nStoreDifference 1\nmobility 2"),
+        Some(DuplicateFeature(Feature::Mobility))
+    );
+    assert_eq!(
+        parse("Mobility high"),
+        Some(InvalidWeight("high".to_string()))
+    );
+    assert_eq!(
+        parse("Mobility 1,5"),
+        Some(InvalidWeight("1,5".to_string()))
+    );
+    assert_eq!(
+        parse("Mobility"),
+        Some(MalformedLine("Mobility".to_string()))
+    );
+    assert_eq!(
+        parse("Mobility 1 2"),
+        Some(MalformedLine("Mobility 1 2".to_string()))
+    );
+}
@@ -0,0 +1,312 @@
+use crate::game::{Board, Game, GameOver, GameState, PlayerSide, SowingEvent, Winner};
+use crate::rng::Rng;
+use crate::rules::{Rules, Sweep, Variant};
+
+use super::{assert_board, board, playable};
+
+/// Plays one pocket of the side to move on a scripted board
+fn play_scripted(variant: Variant, board: Board, pocket: usize) -> Game {
+    let mut game = Game::with_rules(board, variant.rules());
+    game.play_move((pocket, board.player_turn)).unwrap();
+    game
+}
+
+#[test]
+fn scripted_captures() {
+    use PlayerSide::{Opponent, Player};
+    // Kalah: the last stone lands in an empty pocket of the mover, taking the opposite pocket
+    let game = play_scripted(
+        Variant::Kalah,
+        board(&[1, 0, 2, 2, 0], &[3, 3, 3, 3, 0], Player),
+        0,
+    );
+    assert_board(
+        game.board,
+        board(&[0, 0, 2, 2, 4], &[3, 3, 0, 3, 0], Opponent),
+    );
+    assert_eq!(game.game_state, GameState::InProgress);
+    // nothing is taken when the opposite pocket is empty
+    let game = play_scripted(
+        Variant::Kalah,
+        board(&[1, 0, 2, 2, 0], &[3, 3, 0, 3, 0], Player),
+        0,
+    );
+    assert_board(
+        game.board,
+        board(&[0, 1, 2, 2, 0], &[3, 3, 0, 3, 0], Opponent),
+    );
+    // or when the last stone lands in an empty pocket of the opponent
+    let game = play_scripted(
+        Variant::Kalah,
+        board(&[1, 0, 0, 3, 0], &[3, 0, 3, 3, 0], Player),
+        3,
+    );
+    assert_board(
+        game.board,
+        board(&[1, 0, 0, 0, 1], &[4, 1, 3, 3, 0], Opponent),
+    );
+
+    // Oware: pockets of 2 or 3 are taken backwards from the last one until a pocket that isn't
+    let game = play_scripted(
+        Variant::Oware,
+        board(&[0, 0, 0, 3, 0], &[4, 2, 1, 4, 0], Player),
+        3,
+    );
+    assert_board(
+        game.board,
+        board(&[0, 0, 0, 0, 5], &[5, 0, 0, 4, 0], Opponent),
+    );
+    assert_eq!(game.game_state, GameState::InProgress);
+    // the mover's own pockets are never taken
+    let game = play_scripted(
+        Variant::Oware,
+        board(&[0, 1, 1, 1, 0], &[1, 1, 1, 1, 0], Player),
+        1,
+    );
+    assert_board(
+        game.board,
+        board(&[0, 0, 2, 1, 0], &[1, 1, 1, 1, 0], Opponent),
+    );
+
+    // Avalanche: the last stone landing in a non-empty pocket picks it up and keeps going
+    let game = play_scripted(
+        Variant::Avalanche,
+        board(&[1, 2, 0, 0, 0], &[1, 1, 1, 1, 0], Player),
+        0,
+    );
+    assert_board(
+        game.board,
+        board(&[0, 0, 1, 1, 1], &[1, 1, 1, 1, 0], Player),
+    );
+}
+
+#[test]
+fn scripted_skip_stores_laps() {
+    use PlayerSide::{Opponent, Player};
+    // 7 stones go around a 3 pocket board skipping both stores and the emptied pocket
+    let game = play_scripted(
+        Variant::Oware,
+        board(&[7, 1, 1, 0], &[1, 1, 1, 0], Player),
+        0,
+    );
+    assert_board(game.board, board(&[0, 3, 3, 0], &[2, 2, 2, 0], Opponent));
+    // the same for the opponent, going round twice
+    let game = play_scripted(
+        Variant::Oware,
+        board(&[0, 0, 0, 0], &[1, 11, 0, 0], Opponent),
+        1,
+    );
+    assert_board(game.board, board(&[2, 2, 2, 0], &[3, 0, 3, 0], Player));
+}
+
+#[test]
+fn scripted_game_ends() {
+    use PlayerSide::{Opponent, Player};
+    let tied = GameState::Over(GameOver::Win(Winner::Tie));
+    let player_won = GameState::Over(GameOver::Win(Winner::Player));
+    // Kalah ends as soon as either side is empty and sweeps what is left to its owner
+    let game = play_scripted(
+        Variant::Kalah,
+        board(&[0, 0, 0, 1, 10], &[1, 1, 1, 1, 5], Player),
+        3,
+    );
+    assert_board(
+        game.board,
+        board(&[0, 0, 0, 0, 11], &[0, 0, 0, 0, 9], Player),
+    );
+    assert_eq!(game.game_state, player_won);
+    // Game Pigeon ends the same way but leaves the stones where they are
+    let game = play_scripted(
+        Variant::Avalanche,
+        board(&[0, 0, 0, 1, 10], &[1, 1, 1, 1, 5], Player),
+        3,
+    );
+    assert_board(
+        game.board,
+        board(&[0, 0, 0, 0, 11], &[1, 1, 1, 1, 5], Player),
+    );
+    assert_eq!(game.game_state, player_won);
+    // Oware only ends once the side to move has nothing to play
+    let game = play_scripted(
+        Variant::Oware,
+        board(&[0, 0, 0, 1, 10], &[0, 1, 1, 2, 5], Player),
+        3,
+    );
+    assert_board(
+        game.board,
+        board(&[0, 0, 0, 0, 10], &[1, 1, 1, 2, 5], Opponent),
+    );
+    assert_eq!(game.game_state, GameState::InProgress);
+    let game = play_scripted(Variant::Oware, game.board, 0);
+    assert_board(
+        game.board,
+        board(&[0, 0, 0, 0, 10], &[0, 0, 0, 0, 10], Player),
+    );
+    assert_eq!(game.game_state, tied);
+}
+
+#[test]
+fn board_validation() {
+    use crate::game::InvalidBoardError::*;
+    let side = [4, 4, 4, 0];
+    assert!(Board::new(&side, &side, PlayerSide::Player, 4).is_ok());
+    assert_eq!(
+        Board::new(&side, &[4, 4, 0], PlayerSide::Player, 4).err(),
+        Some(MismatchedSides(3, 2))
+    );
+    assert_eq!(
+        Board::new(&[0], &[0], PlayerSide::Player, 4).err(),
+        Some(InvalidPocketCount(0))
+    );
+    assert_eq!(
+        Board::new(&[0; 10], &[0; 10], PlayerSide::Player, 4).err(),
+        Some(InvalidPocketCount(9))
+    );
+    assert_eq!(
+        Board::new(&side, &[4, 4, 5, -1], PlayerSide::Opponent, 4).err(),
+        Some(NegativeStones((3, PlayerSide::Opponent), -1))
+    );
+    assert_eq!(
+        Board::new(&side, &[4, 4, 5, 0], PlayerSide::Player, 4).err(),
+        Some(WrongTotal {
+            expected: 24,
+            total: 25
+        })
+    );
+    // a store past the board's total is reported as such, rather than as a wrong total
+    assert_eq!(
+        Board::new(&side, &[0, 0, 0, 25], PlayerSide::Player, 4).err(),
+        Some(StoreOverflow {
+            side: PlayerSide::Opponent,
+            stones: 25,
+            expected: 24
+        })
+    );
+    assert_eq!(
+        Board::new(&[0, 0, 0, 24], &[0, 0, 0, 0], PlayerSide::Player, 4)
+            .map(|board| board.score(PlayerSide::Player)),
+        Ok(24)
+    );
+    assert_eq!(Board::with_size(9, 4).err(), Some(InvalidPocketCount(9)));
+    // free-form boards only check the shape and the signs
+    let puzzle = Board::free_form(
+        &[5, 3, 2, 0, 1, 7, 12],
+        &[7, 2, 4, 9, 0, 2, 9],
+        PlayerSide::Player,
+    )
+    .unwrap();
+    assert_eq!(puzzle.total_stones(), 63);
+    assert_eq!(
+        puzzle.check_total(4),
+        Err(WrongTotal {
+            expected: 48,
+            total: 63
+        })
+    );
+    assert_eq!(
+        Board::free_form(&[0, 0, 99], &[0, 0, 0], PlayerSide::Player)
+            .unwrap()
+            .check_total(4),
+        Err(StoreOverflow {
+            side: PlayerSide::Player,
+            stones: 99,
+            expected: 16
+        })
+    );
+    assert_eq!(
+        Board::free_form(&[1, -2, 0], &[0, 0, 0], PlayerSide::Player).err(),
+        Some(NegativeStones((1, PlayerSide::Player), -2))
+    );
+    assert_eq!(
+        Board::free_form(&[1, 0], &[0, 0, 0], PlayerSide::Player).err(),
+        Some(MismatchedSides(1, 2))
+    );
+}
+
+/// Applies one traced event to the pockets of each side, store last, and the side to move
+fn replay_event(
+    event: SowingEvent,
+    pockets: &mut [Vec<i32>; 2],
+    turn: &mut PlayerSide,
+    in_hand: &mut i32,
+) {
+    let index = |side: PlayerSide| match side {
+        PlayerSide::Player => 0,
+        PlayerSide::Opponent => 1,
+    };
+    match event {
+        SowingEvent::Pickup((pocket, side), stones)
+        | SowingEvent::Avalanche((pocket, side), stones) => {
+            assert_eq!(*in_hand, 0, "{:?} while sowing", event);
+            assert_eq!(pockets[index(side)][pocket], stones, "{:?}", event);
+            pockets[index(side)][pocket] = 0;
+            *in_hand = stones;
+        }
+        SowingEvent::Drop((pocket, side), left) | SowingEvent::Store((pocket, side), left) => {
+            pockets[index(side)][pocket] += 1;
+            *in_hand -= 1;
+            assert_eq!(*in_hand, left, "{:?}", event);
+        }
+        SowingEvent::Capture((pocket, side), stones) => {
+            assert_eq!(pockets[index(side)][pocket], stones, "{:?}", event);
+            pockets[index(side)][pocket] = 0;
+            *pockets[index(*turn)].last_mut().unwrap() += stones;
+        }
+        SowingEvent::Sweep => {
+            for side in pockets.iter_mut() {
+                let (store, side_pockets) = side.split_last_mut().unwrap();
+                *store += side_pockets.iter().sum::<i32>();
+                side_pockets.fill(0);
+            }
+        }
+        SowingEvent::TurnSwitch(side) => *turn = side,
+    }
+}
+
+#[test]
+fn trace_replays_to_the_final_board() {
+    let mut rng = Rng(11);
+    for variant in Variant::ALL {
+        for sweep in [Sweep::None, Sweep::ToOwner] {
+            let rules = Rules {
+                sweep,
+                ..variant.rules()
+            };
+            for (pockets, stones) in [(6, 4), (4, 5)] {
+                for _ in 0..10 {
+                    let mut game =
+                        Game::with_rules(Board::with_size(pockets, stones).unwrap(), rules);
+                    while game.game_state == GameState::InProgress {
+                        let side = game.board.player_turn;
+                        let store = game.board.store();
+                        let playable = playable(&game);
+                        let pocket = playable[rng.below(playable.len())];
+                        let mut replayed = [
+                            game.board.player_pockets[..=store].to_vec(),
+                            game.board.opponent_pockets[..=store].to_vec(),
+                        ];
+                        let mut turn = side;
+                        let mut in_hand = 0;
+                        let steps = game.play_move_traced((pocket, side)).unwrap();
+                        assert!(matches!(
+                            steps[0].event,
+                            SowingEvent::Pickup(location, _) if location == (pocket, side)
+                        ));
+                        for step in &steps {
+                            replay_event(step.event, &mut replayed, &mut turn, &mut in_hand);
+                            assert_board(
+                                step.board,
+                                Board::free_form(&replayed[0], &replayed[1], turn).unwrap(),
+                            );
+                        }
+                        assert_eq!(in_hand, 0);
+                        assert_board(
+                            game.board,
+                            Board::free_form(&replayed[0], &replayed[1], turn).unwrap(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::error::Error;
+use crate::game::{Game, GameState, PlayerSide};
+use crate::mcts::{distinct_turns, MctsConfig, MctsTree, Playout};
+use crate::transposition::TranspositionTable;
+
+use super::{finished_games, game_line, play_turn, random_turns, small_positions, starts};
+
+#[test]
+fn mcts_is_reproducible() {
+    for game in small_positions() {
+        for playout in [Playout::Greedy, Playout::Random] {
+            let config = MctsConfig {
+                iterations: 300,
+                playout,
+                seed: 5,
+                ..Default::default()
+            };
+            let first = MctsTree::new(game, config).search().unwrap();
+            let second = MctsTree::new(game, config).search().unwrap();
+            assert_eq!(first.sequence, second.sequence, "{}", game);
+            assert_eq!(first.visits, second.visits);
+            assert_eq!(first.win_rate, second.win_rate);
+            assert_eq!(first.iterations, 300);
+        }
+    }
+}
+
+/// Whether `side` has won the game
+fn won_by(game: &Game, side: PlayerSide) -> bool {
+    let won = match side {
+        PlayerSide::Player => 1.0,
+        PlayerSide::Opponent => 0.0,
+    };
+    game.player_result() == Some(won)
+}
+
+/// Positions from random games where the side to move has a turn that wins the game, and every
+/// other turn lets the opponent win with their reply
+fn forced_win_positions() -> Vec<Game> {
+    let turns = |game: &Game| distinct_turns(game, &mut TranspositionTable::default());
+    let forced = |game: &Game| {
+        let side = game.board.player_turn;
+        let options = turns(game);
+        options.iter().any(|(_, after)| won_by(after, side))
+            && options.iter().all(|(_, after)| {
+                won_by(after, side)
+                    || (after.game_state == GameState::InProgress
+                        && turns(after)
+                            .iter()
+                            .any(|(_, reply)| won_by(reply, after.board.player_turn)))
+            })
+    };
+    let mut positions = Vec::new();
+    for start in starts(&[(4, 3)]) {
+        for seed in 0..40 {
+            let line = game_line(start, usize::MAX, random_turns(seed));
+            positions.extend(
+                line.into_iter()
+                    .find(|game| game.game_state == GameState::InProgress && forced(game)),
+            );
+        }
+    }
+    positions
+}
+
+#[test]
+fn mcts_finds_a_forced_win() {
+    let positions = forced_win_positions();
+    assert!(positions.len() >= 20, "{} positions", positions.len());
+    for game in positions {
+        let side = game.board.player_turn;
+        for playout in [Playout::Greedy, Playout::Random] {
+            let config = MctsConfig {
+                playout,
+                ..Default::default()
+            };
+            let result = MctsTree::new(game, config).search().unwrap();
+            let mut played = game;
+            play_turn(&mut played, &result.sequence);
+            assert!(won_by(&played, side), "{:?} from {}", result.sequence, game);
+            assert_eq!(result.win_rate, 1.0);
+        }
+    }
+}
+
+#[test]
+fn search_has_no_moves_in_a_finished_game() {
+    for game in finished_games() {
+        assert!(matches!(
+            MctsTree::new(game, MctsConfig::default()).search(),
+            Err(Error::NoMoves)
+        ));
+    }
+}
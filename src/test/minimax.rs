@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+use crate::error::Error;
+use crate::eval::StoreDifference;
+use crate::game::{Game, GameState, PlayerSide};
+use crate::minimax::{evaluate_game, GameTree};
+use crate::solver::{SequenceNodeEnum, SequenceTree};
+use crate::transposition::TranspositionTable;
+
+use super::{finished_games, play_turn, small_positions};
+
+/// Plain minimax over whole turns without pruning or a transposition table, scoring positions
+/// the same way as `GameTree`
+fn plain_minimax(game: Game, depth: usize) -> f32 {
+    if depth == 0 || game.game_state != GameState::InProgress {
+        return evaluate_game(&game, &StoreDifference);
+    }
+    let mut tree = SequenceTree::new(game);
+    tree.generate_tree(game.board.player_turn, None);
+    let turns = tree
+        .leaf_nodes
+        .iter()
+        .filter_map(|leaf| match tree.nodes[*leaf].node_enum {
+            SequenceNodeEnum::Move(ref move_node) => Some(move_node.r#move.game),
+            SequenceNodeEnum::Root(_) => None,
+        })
+        .collect::<HashSet<Game>>();
+    let values = turns.into_iter().map(|turn| plain_minimax(turn, depth - 1));
+    let value = match game.board.player_turn {
+        PlayerSide::Player => values.reduce(f32::max),
+        PlayerSide::Opponent => values.reduce(f32::min),
+    };
+    value.unwrap_or_else(|| evaluate_game(&game, &StoreDifference))
+}
+
+#[test]
+fn alpha_beta_matches_plain_minimax() {
+    for game in small_positions() {
+        // one table kept across depths, like the CLI does, must not change any value either
+        let mut shared_table = TranspositionTable::default();
+        for depth in 1..=3 {
+            let expected = plain_minimax(game, depth);
+            let fresh = GameTree::new(game)
+                .search(depth, &mut TranspositionTable::default())
+                .unwrap();
+            assert_eq!(fresh.value, expected, "depth {} of {}", depth, game);
+            let shared = GameTree::new(game)
+                .search(depth, &mut shared_table)
+                .unwrap();
+            assert_eq!(
+                shared.value, expected,
+                "depth {} of {} with a shared table",
+                depth, game
+            );
+            // the chosen turn must lead to a position worth the root's value
+            let mut after = game;
+            play_turn(&mut after, &fresh.sequence);
+            assert_eq!(plain_minimax(after, depth - 1), expected);
+        }
+    }
+}
+
+#[test]
+fn search_has_no_moves_in_a_finished_game() {
+    for game in finished_games() {
+        let mut table = TranspositionTable::default();
+        for depth in 0..3 {
+            assert!(matches!(
+                GameTree::new(game).search(depth, &mut table),
+                Err(Error::NoMoves)
+            ));
+        }
+    }
+    // a search with no depth left doesn't choose a turn either
+    assert!(matches!(
+        GameTree::new(Game::default()).search(0, &mut TranspositionTable::default()),
+        Err(Error::NoMoves)
+    ));
+}
@@ -0,0 +1,125 @@
+/*
+The tests are grouped by the module they cover. Positions come from the fixtures below: the start
+of every variant on a few board sizes, and the positions along a game played from one of them.
+*/
+
+use crate::game::{Board, Game, GameState, PlayerSide, PocketIndex};
+use crate::minimax::GameTree;
+use crate::player::{Player, RandomPlayer};
+use crate::rules::Variant;
+use crate::transposition::TranspositionTable;
+
+mod eval;
+mod game;
+mod mcts;
+mod minimax;
+mod player;
+mod position;
+#[cfg(feature = "qlearning")]
+mod qlearning_move;
+mod record;
+mod solver;
+mod tournament;
+mod transposition;
+mod tuning;
+mod zobrist;
+
+/// The free-form puzzle position from the README
+const README_POSITION: &str = "5,3,2,0,1,7/12 7,2,4,9,0,2/9 P";
+
+/// The starting position of every variant on each board size
+fn starts(sizes: &[(usize, i32)]) -> Vec<Game> {
+    Variant::ALL
+        .into_iter()
+        .flat_map(|variant| {
+            sizes.iter().map(move |(pockets, stones)| {
+                Game::with_rules(
+                    Board::with_size(*pockets, *stones).unwrap(),
+                    variant.rules(),
+                )
+            })
+        })
+        .collect()
+}
+
+/// The positions of a game from `start`, one after each turn (or the part of one) chosen by
+/// `choose`, until `turns` have been chosen or the game is over. The first position is `start`.
+fn game_line(
+    start: Game,
+    turns: usize,
+    mut choose: impl FnMut(&Game) -> Vec<PocketIndex>,
+) -> Vec<Game> {
+    let mut game = start;
+    let mut line = vec![game];
+    for _ in 0..turns {
+        if game.game_state != GameState::InProgress {
+            break;
+        }
+        let turn = choose(&game);
+        play_turn(&mut game, &turn);
+        line.push(game);
+    }
+    line
+}
+
+/// The turn minimax picks searching 1 turn ahead
+fn search_turn(game: &Game) -> Vec<PocketIndex> {
+    GameTree::new(*game)
+        .search(1, &mut TranspositionTable::default())
+        .unwrap()
+        .sequence
+}
+
+/// Chooses random turns, the same ones for the same seed
+fn random_turns(seed: u64) -> impl FnMut(&Game) -> Vec<PocketIndex> {
+    let mut player = RandomPlayer::new(seed);
+    move |game| player.choose_turn(game).unwrap()
+}
+
+/// Positions from the start and the middle of a game on small boards of every variant
+fn small_positions() -> Vec<Game> {
+    starts(&[(4, 3), (3, 4)])
+        .into_iter()
+        .flat_map(|start| game_line(start, 3, search_turn))
+        .filter(|game| game.game_state == GameState::InProgress)
+        .collect()
+}
+
+/// A game of each variant on a small board played to the end
+fn finished_games() -> Vec<Game> {
+    starts(&[(3, 2)])
+        .into_iter()
+        .map(|start| *game_line(start, usize::MAX, search_turn).last().unwrap())
+        .collect()
+}
+
+/// Plays every pocket of a turn for the side to move
+fn play_turn(game: &mut Game, turn: &[PocketIndex]) {
+    let side = game.board.player_turn;
+    for pocket in turn {
+        game.play_move((*pocket, side)).unwrap();
+    }
+}
+
+/// The pockets the side to move can play
+fn playable(game: &Game) -> Vec<PocketIndex> {
+    let side = game.board.player_turn;
+    (0..game.board.pockets())
+        .filter(|pocket| game.board.get_stones((*pocket, side)) > 0)
+        .collect()
+}
+
+/// A board from each side's pockets, store last
+fn board(player: &[i32], opponent: &[i32], player_turn: PlayerSide) -> Board {
+    Board::free_form(player, opponent, player_turn).unwrap()
+}
+
+/// Boards have no `Debug`, so they are compared showing their position notation
+fn assert_board(actual: Board, expected: Board) {
+    assert!(
+        actual == expected,
+        "expected {} but got {}",
+        expected,
+        actual
+    );
+}
@@ -0,0 +1,86 @@
+use crate::error::Error;
+use crate::game::{Game, GameState};
+use crate::mcts::MctsConfig;
+use crate::player::{GreedyPlayer, HumanPlayer, MctsPlayer, MinimaxPlayer, Player, RandomPlayer};
+
+#[cfg(feature = "qlearning")]
+use super::qlearning_move::trained_policy;
+use super::{finished_games, game_line, random_turns, starts};
+
+/// One of each player, with small budgets so the tests stay quick
+#[cfg_attr(not(feature = "qlearning"), allow(unused_variables))]
+fn players(start: Game) -> Vec<Box<dyn Player>> {
+    vec![
+        Box::new(GreedyPlayer::new()),
+        Box::new(MinimaxPlayer::new(2)),
+        Box::new(MctsPlayer::new(MctsConfig {
+            iterations: 200,
+            ..Default::default()
+        })),
+        Box::new(RandomPlayer::new(1)),
+        #[cfg(feature = "qlearning")]
+        Box::new(trained_policy(start, 50)),
+    ]
+}
+
+/// Plays `turn` for the side to move, checking every pocket is legal and that the turn passes (or
+/// the game ends) after the last pocket and not before
+fn assert_legal_turn(game: &Game, turn: &[usize], player: &str) {
+    assert!(!turn.is_empty(), "{} chose no pockets in {}", player, game);
+    let side = game.board.player_turn;
+    let mut after = *game;
+    for (played, pocket) in turn.iter().enumerate() {
+        assert!(
+            after.game_state == GameState::InProgress && after.board.player_turn == side,
+            "{} kept playing after its turn ended in {}: {:?}",
+            player,
+            game,
+            turn
+        );
+        assert!(
+            after.play_move((*pocket, side)).is_ok(),
+            "{} chose illegal pocket {} (move {}) in {}",
+            player,
+            pocket,
+            played,
+            game
+        );
+    }
+    assert!(
+        after.game_state != GameState::InProgress || after.board.player_turn != side,
+        "{} stopped before its turn ended in {}: {:?}",
+        player,
+        game,
+        turn
+    );
+}
+
+#[test]
+fn players_choose_legal_turns() {
+    for start in starts(&[(4, 3)]) {
+        let mid_game = *game_line(start, 6, random_turns(7)).last().unwrap();
+        assert_eq!(mid_game.game_state, GameState::InProgress);
+        for mut player in players(start) {
+            for game in [start, start.mirrored(), mid_game] {
+                let turn = player.choose_turn(&game).unwrap();
+                assert_legal_turn(&game, &turn, &player.name());
+            }
+        }
+    }
+}
+
+#[test]
+fn players_have_no_moves_in_a_finished_game() {
+    for (start, game) in starts(&[(3, 2)]).into_iter().zip(finished_games()) {
+        let mut players = players(start);
+        players.push(Box::new(HumanPlayer));
+        for mut player in players {
+            assert!(
+                matches!(player.choose_turn(&game), Err(Error::NoMoves)),
+                "{} chose a turn in {}",
+                player.name(),
+                game
+            );
+        }
+    }
+}
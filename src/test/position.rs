@@ -0,0 +1,113 @@
+use crate::game::{Board, Game, GameOver, GameState, PlayerSide, Winner};
+use crate::rules::{Rules, Sweep};
+
+use super::{assert_board, finished_games, game_line, playable, starts, README_POSITION};
+
+#[test]
+fn position_round_trip() {
+    let mut games = Vec::new();
+    for start in starts(&[(6, 4), (4, 3), (1, 2), (8, 6)]) {
+        for sweep in [Sweep::None, Sweep::ToOwner] {
+            let rules = Rules {
+                sweep,
+                ..start.rules
+            };
+            // a few moves in, so the pockets differ and it may be the Opponent's turn
+            games.extend(game_line(Game::with_rules(start.board, rules), 3, |game| {
+                vec![*playable(game).last().unwrap()]
+            }));
+        }
+    }
+    // a free-form position with stones counts past 9
+    games.push(README_POSITION.parse().unwrap());
+    games.extend(finished_games());
+    for game in games {
+        let board = game.board;
+        assert_board(board.to_string().parse::<Board>().unwrap(), board);
+        assert_board(
+            board.mirrored().to_string().parse().unwrap(),
+            board.mirrored(),
+        );
+        // the game state isn't written, but it follows from the board
+        assert_eq!(game.to_string().parse::<Game>(), Ok(game));
+    }
+}
+
+#[test]
+fn finished_positions_parse_as_finished_games() {
+    let state = |s: &str| s.parse::<Game>().unwrap().game_state;
+    // an empty side ends Kalah, sweeping the other side's stones to its owner
+    let game = "0,0,0/5 1,2,0/4 P Kalah".parse::<Game>().unwrap();
+    assert_eq!(
+        game.game_state,
+        GameState::Over(GameOver::Win(Winner::Opponent))
+    );
+    assert_eq!(game.to_string(), "0,0,0/5 0,0,0/7 P Kalah");
+    assert_eq!(
+        state("0,0,0/5 1,2,0/4 P Kalah nosweep"),
+        GameState::Over(GameOver::Win(Winner::Player))
+    );
+    // Oware only ends when the side to move has nothing to play
+    assert_eq!(state("0,0,0/5 1,2,0/4 O Oware"), GameState::InProgress);
+    assert_eq!(
+        state("0,0,0/5 1,2,0/4 P Oware"),
+        GameState::Over(GameOver::Win(Winner::Opponent))
+    );
+    // a lead larger than the stones left is a technical win
+    assert_eq!(
+        state("1,1,1/20 1,1,1/3 O"),
+        GameState::Over(GameOver::TechnicalWin(PlayerSide::Player))
+    );
+    assert_eq!(state("1,1,1/9 1,1,1/3 O"), GameState::InProgress);
+}
+
+#[test]
+fn position_parse_errors() {
+    use crate::game::InvalidBoardError;
+    use crate::position::ParsePositionError::*;
+    let board = |s: &str| s.parse::<Board>().err();
+    let game = |s: &str| s.parse::<Game>().err();
+    assert_eq!(board(""), Some(MissingField));
+    assert_eq!(board("4,4/0 4,4/0"), Some(MissingField));
+    assert_eq!(
+        board("4,4/0 4,4/0 P x"),
+        Some(TrailingField("x".to_string()))
+    );
+    assert_eq!(
+        board("4,4,0 4,4/0 P"),
+        Some(MalformedSide("4,4,0".to_string()))
+    );
+    assert_eq!(board("4,a/0 4,4/0 P"), Some(InvalidStones("a".to_string())));
+    assert_eq!(board("4,4/ 4,4/0 P"), Some(InvalidStones("".to_string())));
+    assert_eq!(
+        board("4,4/0 4,4,4/0 P"),
+        Some(InvalidBoard(InvalidBoardError::MismatchedSides(2, 3)))
+    );
+    assert_eq!(
+        board("4,-1/0 4,4/0 P"),
+        Some(InvalidBoard(InvalidBoardError::NegativeStones(
+            (1, PlayerSide::Player),
+            -1
+        )))
+    );
+    let nine = "1,1,1,1,1,1,1,1,1/0";
+    assert_eq!(
+        board(&format!("{} {} P", nine, nine)),
+        Some(InvalidBoard(InvalidBoardError::InvalidPocketCount(9)))
+    );
+    assert_eq!(board("4,4/0 4,4/0 X"), Some(InvalidTurn("X".to_string())));
+    assert_eq!(
+        game("4,4/0 4,4/0 P Chess"),
+        Some(InvalidVariant("Chess".to_string()))
+    );
+    assert_eq!(
+        game("4,4/0 4,4/0 P Kalah always"),
+        Some(InvalidSweep("always".to_string()))
+    );
+    assert_eq!(
+        game("4,4/0 4,4/0 P Kalah sweep x"),
+        Some(TrailingField("x".to_string()))
+    );
+    // the turn and variant are read case-insensitively
+    assert!(game("4,4/0 4,4/0 o kalah").is_none());
+}
@@ -0,0 +1,57 @@
+use crate::game::{Board, Game};
+use crate::qlearning_move::{QLearningConfig, QPolicy, QTableError, QTABLE_VERSION};
+use crate::rules::{Sweep, Variant};
+
+/// A policy trained from `start` for the given number of self-play games
+pub(super) fn trained_policy(start: Game, episodes: usize) -> QPolicy {
+    let mut policy = QPolicy::new();
+    let config = QLearningConfig {
+        episodes,
+        ..Default::default()
+    };
+    policy.train(start, &config);
+    policy
+}
+
+#[test]
+fn q_learning_finds_the_free_move() {
+    // Only the middle pocket ends in the store; the other two hand the turn over
+    let start = Game::with_rules(Board::with_size(3, 2).unwrap(), Variant::Kalah.rules());
+    let policy = trained_policy(start, 1000);
+    assert_eq!(policy.choose_move(&start), Some(1));
+    assert_eq!(policy.choose_move(&start.mirrored()), Some(1));
+    let free = policy.value(&start, 1).unwrap();
+    for pocket in [0, 2] {
+        assert!(policy
+            .value(&start, pocket)
+            .is_none_or(|value| value < free));
+    }
+}
+
+#[test]
+fn saved_q_table_loads_the_same_policy() {
+    let mut rules = Variant::Kalah.rules();
+    rules.sweep = Sweep::None;
+    let start = Game::with_rules(Board::with_size(3, 3).unwrap(), rules);
+    let policy = trained_policy(start, 500);
+    let bytes = policy.to_bytes().unwrap();
+    let loaded = QPolicy::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded.header(), policy.header());
+    assert_eq!(loaded.table(), policy.table());
+    assert_eq!(loaded.to_bytes().unwrap(), bytes);
+
+    let mut newer = bytes.clone();
+    newer[4] = QTABLE_VERSION + 1;
+    assert_eq!(
+        QPolicy::from_bytes(&newer).err(),
+        Some(QTableError::UnsupportedVersion(QTABLE_VERSION + 1))
+    );
+    assert_eq!(
+        QPolicy::from_bytes(&bytes[..bytes.len() - 1]).err(),
+        Some(QTableError::Truncated)
+    );
+    assert_eq!(
+        QPolicy::from_bytes(b"not a table").err(),
+        Some(QTableError::NotAQTable)
+    );
+}
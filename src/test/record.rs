@@ -0,0 +1,114 @@
+use crate::game::{Board, Game, GameState};
+use crate::record::{result_tag, GameRecord, ParseRecordError};
+use crate::rules::Variant;
+
+use super::{playable, starts};
+
+#[test]
+fn record_undo_redo_and_jump() {
+    let mut record = GameRecord::new(Game::with_rules(
+        Board::with_size(4, 3).unwrap(),
+        Variant::Kalah.rules(),
+    ));
+    assert!(!record.undo());
+    assert!(!record.redo());
+    assert!(record.jump_to(0));
+    assert!(!record.jump_to(1));
+    // play the first playable pocket until the game ends, replaying each move by hand alongside
+    let mut game = *record.start();
+    let mut games = vec![game];
+    while game.game_state == GameState::InProgress {
+        let pocket = playable(&game)[0];
+        game.play_move((pocket, game.board.player_turn)).unwrap();
+        record.play_move(pocket).unwrap();
+        games.push(game);
+        assert_eq!(*record.current(), game);
+        assert_eq!(record.ply(), games.len() - 1);
+    }
+    let moves = record.entries().len();
+    assert_eq!(moves, games.len() - 1);
+    for (entry, game) in record.entries().iter().zip(&games[1..]) {
+        assert_eq!(entry.game, *game);
+    }
+    assert!(!record.redo());
+    assert!(!record.jump_to(moves + 1));
+    assert_eq!(record.ply(), moves);
+    // every ply shows the game after that many moves, going either way
+    for ply in (0..moves).rev() {
+        assert!(record.undo());
+        assert_eq!(*record.current(), games[ply]);
+    }
+    assert!(!record.undo());
+    for game in &games[1..] {
+        assert!(record.redo());
+        assert_eq!(record.current(), game);
+    }
+    for ply in [moves, 0, moves / 2, 1] {
+        assert!(record.jump_to(ply));
+        assert_eq!(record.ply(), ply);
+        assert_eq!(*record.current(), games[ply]);
+    }
+    // a new move after stepping back discards the undone moves
+    assert!(record.redo());
+    let pocket = *playable(record.current()).last().unwrap();
+    record.play_move(pocket).unwrap();
+    assert_eq!(record.entries().len(), 3);
+    assert_eq!(record.ply(), 3);
+    assert!(!record.redo());
+    assert!(!record.jump_to(4));
+    assert_ne!(*record.current(), games[3]);
+    assert_eq!(record.entries()[1].game, games[2]);
+}
+
+/// A record of each variant on a few board sizes, played with the first playable pocket until
+/// the game ends, and the same record stepped back halfway
+fn sample_records() -> Vec<GameRecord> {
+    let mut records = Vec::new();
+    for start in starts(&[(6, 4), (4, 3), (2, 5)]) {
+        let mut record = GameRecord::new(start);
+        // Oware can go round in circles, so long games are cut short
+        while record.current().game_state == GameState::InProgress && record.ply() < 200 {
+            record.play_move(playable(record.current())[0]).unwrap();
+        }
+        records.push(record.clone());
+        record.player_name = Some("Ann".to_string());
+        record.jump_to(record.entries().len() / 2);
+        records.push(record);
+    }
+    records
+}
+
+#[test]
+fn record_round_trip() {
+    for record in sample_records() {
+        let text = record.to_string();
+        let parsed = text.parse::<GameRecord>().unwrap();
+        assert_eq!(parsed.start(), record.start());
+        assert_eq!(parsed.entries(), &record.entries()[..record.ply()]);
+        assert_eq!(parsed.ply(), record.ply());
+        assert_eq!(parsed.current(), record.current());
+        assert_eq!(parsed.player_name, record.player_name);
+        assert_eq!(parsed.opponent_name, record.opponent_name);
+        assert_eq!(parsed.to_string(), text);
+    }
+}
+
+#[test]
+fn record_result_must_match_the_moves() {
+    for record in sample_records() {
+        let text = record.to_string();
+        for result in ["Player", "Opponent", "Tie", "*"] {
+            let changed = text.replace(
+                &format!("[Result \"{}\"]", result_tag(record.current())),
+                &format!("[Result \"{}\"]", result),
+            );
+            match changed == text {
+                true => assert!(changed.parse::<GameRecord>().is_ok()),
+                false => assert!(matches!(
+                    changed.parse::<GameRecord>(),
+                    Err(ParseRecordError::WrongResult { .. })
+                )),
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::eval::{Evaluator, StoreDifference};
+use crate::game::{Board, Game, GameState, PlayerSide};
+use crate::solver::{SequenceNode, SequenceNodeEnum, SequenceTree, SequenceTreeIndex};
+
+use super::{play_turn, small_positions, starts, README_POSITION};
+
+/// Generates the tree of the player to move both ways and checks that every node, leaf and
+/// chosen turn is the same
+fn assert_parallel_matches_sequential(game: Game) {
+    let player_turn = game.board.player_turn;
+    let mut sequential = SequenceTree::new(game);
+    sequential.generate_tree(player_turn, None);
+    let mut parallel = SequenceTree::new(game);
+    parallel.generate_tree_parallel(player_turn);
+    assert_eq!(
+        sequential.nodes, parallel.nodes,
+        "nodes differ for {}",
+        game
+    );
+    assert_eq!(sequential.leaf_nodes, parallel.leaf_nodes);
+    assert_eq!(sequential.game_over_nodes, parallel.game_over_nodes);
+    assert_eq!(sequential.size(), parallel.size());
+    let best = |tree: &SequenceTree| tree.get_best_sequence(&StoreDifference, true, &player_turn);
+    assert_eq!(best(&sequential).ok(), best(&parallel).ok());
+    for leaf in &sequential.leaf_nodes {
+        assert_eq!(
+            sequential.get_move_sequence(*leaf).ok(),
+            parallel.get_move_sequence(*leaf).ok()
+        );
+    }
+}
+
+#[test]
+fn parallel_tree_matches_sequential_on_readme_position() {
+    assert_parallel_matches_sequential(README_POSITION.parse().unwrap());
+}
+
+#[test]
+fn parallel_tree_matches_sequential_on_openings() {
+    for game in starts(&[(6, 4), (4, 3), (3, 5), (1, 1)]) {
+        assert_parallel_matches_sequential(game);
+    }
+}
+
+#[test]
+fn parallel_tree_matches_sequential_through_a_game() {
+    for mut game in starts(&[(6, 4)]) {
+        while game.game_state == GameState::InProgress {
+            assert_parallel_matches_sequential(game);
+            let mut tree = SequenceTree::new(game);
+            tree.generate_tree_parallel(game.board.player_turn);
+            let Ok(sequence) =
+                tree.get_best_sequence(&StoreDifference, true, &game.board.player_turn)
+            else {
+                break;
+            };
+            play_turn(&mut game, &sequence);
+        }
+    }
+}
+
+#[test]
+fn parallel_tree_of_finished_game_has_only_the_root() {
+    let mut game = "0,0,0/20 1,0,0/3 O".parse::<Game>().unwrap();
+    game.play_move((0, game.board.player_turn)).unwrap();
+    assert_ne!(game.game_state, GameState::InProgress);
+    assert_parallel_matches_sequential(game);
+    let mut tree = SequenceTree::new(game);
+    tree.generate_tree_parallel(game.board.player_turn);
+    assert_eq!(tree.size().nodes, 1);
+    assert!(tree.leaf_nodes.is_empty());
+}
+
+#[test]
+fn best_sequences_are_ranked_and_deduplicated() {
+    let mut games = small_positions();
+    games.push(README_POSITION.parse().unwrap());
+    games.push(Game::default().mirrored());
+    for game in games {
+        let side = game.board.player_turn;
+        let mut tree = SequenceTree::new(game);
+        tree.generate_tree(side, None);
+        let ranked = tree
+            .get_best_sequences(&StoreDifference, usize::MAX, false, &side)
+            .unwrap();
+        // the first sequence found for each board, which is the one that is kept
+        let mut first_found = Vec::new();
+        for leaf in &tree.leaf_nodes {
+            if let SequenceNodeEnum::Move(ref move_node) = tree.nodes[*leaf].node_enum {
+                if !first_found
+                    .iter()
+                    .any(|(board, _)| *board == move_node.r#move.game.board)
+                {
+                    first_found.push((move_node.r#move.game.board, *leaf));
+                }
+            }
+        }
+        assert_eq!(ranked.len(), first_found.len(), "{}", game);
+        let found_at = |board: Board| {
+            first_found
+                .iter()
+                .position(|(found, _)| *found == board)
+                .unwrap()
+        };
+        for turn in &ranked {
+            let (_, leaf) = first_found[found_at(turn.game.board)];
+            assert_eq!(turn.sequence, tree.get_move_sequence(leaf).unwrap());
+            assert_eq!(turn.evaluation, StoreDifference.evaluate(&turn.game));
+            let mut played = game;
+            play_turn(&mut played, &turn.sequence);
+            assert_eq!(played, turn.game);
+        }
+        for pair in ranked.windows(2) {
+            let ordered = match side {
+                PlayerSide::Player => pair[0].evaluation >= pair[1].evaluation,
+                PlayerSide::Opponent => pair[0].evaluation <= pair[1].evaluation,
+            };
+            assert!(ordered, "{}", game);
+            // ties keep the order the boards were first found in
+            if pair[0].evaluation == pair[1].evaluation {
+                assert!(found_at(pair[0].game.board) < found_at(pair[1].game.board));
+            }
+        }
+        // fewer turns are the start of the same ranking, with the best turn first
+        for k in [1, 2, 5] {
+            let top = tree
+                .get_best_sequences(&StoreDifference, k, false, &side)
+                .unwrap();
+            assert_eq!(top.len(), k.min(ranked.len()));
+            for (turn, expected) in top.iter().zip(&ranked) {
+                assert_eq!(turn.sequence, expected.sequence);
+            }
+        }
+        assert_eq!(
+            ranked[0].sequence,
+            tree.get_best_sequence(&StoreDifference, false, &side)
+                .unwrap()
+        );
+    }
+}
+
+#[test]
+fn readme_tree_size() {
+    let game = README_POSITION.parse::<Game>().unwrap();
+    let mut tree = SequenceTree::new(game);
+    tree.generate_tree_parallel(game.board.player_turn);
+    let size = tree.size();
+    // the figures quoted in the README
+    assert_eq!(size.nodes, 1_117);
+    #[cfg(target_pointer_width = "64")]
+    assert_eq!(size.bytes, 159_544);
+    // each node costs itself and an index into each of the leaf lists, with the vectors
+    // allocating at most twice what they hold; storing a node's path would not fit
+    let per_node =
+        std::mem::size_of::<SequenceNode>() + 3 * std::mem::size_of::<SequenceTreeIndex>();
+    assert!(
+        size.bytes <= std::mem::size_of::<SequenceTree>() + 2 * per_node * size.nodes,
+        "{} bytes for {} nodes",
+        size.bytes,
+        size.nodes
+    );
+    assert!(size.bytes >= std::mem::size_of::<SequenceNode>() * size.nodes);
+}
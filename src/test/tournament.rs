@@ -0,0 +1,227 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::game::{Board, Game, PlayerSide, PocketIndex};
+use crate::minimax::GameTree;
+use crate::player::{MinimaxPlayer, Player, RandomPlayer};
+use crate::rules::Variant;
+use crate::tournament::{
+    balanced_openings, play_match, round_robin, Crosstable, MatchConfig, MatchResult,
+};
+use crate::transposition::TranspositionTable;
+
+use super::starts;
+
+/// Every turn a `Quitter` was asked for: its name, the position and the side to move
+type Calls = Rc<RefCell<Vec<(String, Game, PlayerSide)>>>;
+
+/// Gives up as soon as it is asked for a turn, either with an error or, if `illegal` is set, with
+/// a turn that plays its store
+struct Quitter {
+    name: String,
+    illegal: bool,
+    calls: Calls,
+}
+
+impl Quitter {
+    fn new(name: &str, illegal: bool, calls: &Calls) -> Self {
+        Quitter {
+            name: name.to_string(),
+            illegal,
+            calls: calls.clone(),
+        }
+    }
+}
+
+impl Player for Quitter {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose_turn(&mut self, game: &Game) -> Result<Vec<PocketIndex>, Error> {
+        self.calls
+            .borrow_mut()
+            .push((self.name.clone(), *game, game.board.player_turn));
+        match self.illegal {
+            true => Ok(vec![game.board.store()]),
+            false => Err(Error::NoMoves),
+        }
+    }
+}
+
+#[test]
+fn match_result_elo() {
+    let result = MatchResult {
+        wins: 12,
+        losses: 4,
+        draws: 4,
+        ..Default::default()
+    };
+    assert_eq!(result.games(), 20);
+    assert_eq!(result.score(), 0.7);
+    // a 70% score is about 147 Elo
+    assert!((result.elo() - 147.2).abs() < 0.1);
+    assert!(result.elo_error() > 0.0 && result.elo_error().is_finite());
+    let reversed = result.reversed();
+    assert!((reversed.elo() + result.elo()).abs() < 1e-9);
+    assert_eq!(reversed.reversed(), result);
+
+    let sweep = MatchResult {
+        wins: 10,
+        ..Default::default()
+    };
+    assert_eq!(sweep.elo(), f64::INFINITY);
+    assert_eq!(sweep.elo_error(), f64::INFINITY);
+}
+
+#[test]
+fn players_alternate_sides() {
+    let calls = Calls::default();
+    let config = MatchConfig {
+        games: 6,
+        ..Default::default()
+    };
+    let mut quitter = Quitter::new("Quitter", false, &calls);
+    let mut random = RandomPlayer::new(1);
+    let result = play_match(Game::default(), &mut quitter, &mut random, &config);
+    // the quitter gives up the first time it is to move, so it is asked once a game
+    let sides = calls
+        .borrow()
+        .iter()
+        .map(|(_, _, side)| *side)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sides,
+        [PlayerSide::Player, PlayerSide::Opponent].repeat(config.games / 2)
+    );
+    assert_eq!(result.losses, config.games);
+}
+
+#[test]
+fn giving_up_loses_even_when_ahead() {
+    // the Player leads by 10 with 12 stones left, so the game isn't decided yet
+    let start = "2,2,2/10 2,2,2/0 P".parse::<Game>().unwrap();
+    let config = MatchConfig {
+        games: 1,
+        ..Default::default()
+    };
+    for illegal in [false, true] {
+        let calls = Calls::default();
+        let mut quitter = Quitter::new("Quitter", illegal, &calls);
+        let mut random = RandomPlayer::new(1);
+        let result = play_match(start, &mut quitter, &mut random, &config);
+        assert_eq!((result.wins, result.losses, result.draws), (0, 1, 0));
+        assert_eq!(result.total_margin, 10);
+        assert_eq!(result.technical_wins + result.technical_losses, 0);
+        assert_eq!(calls.borrow().len(), 1);
+        // and the other player wins by it, from the other seat
+        let mut quitter = Quitter::new("Quitter", illegal, &calls);
+        let mut random = RandomPlayer::new(1);
+        let result = play_match(start, &mut random, &mut quitter, &config);
+        assert_eq!((result.wins, result.losses, result.draws), (1, 0, 0));
+    }
+}
+
+#[test]
+fn balanced_openings_are_balanced_and_played_from_both_sides() {
+    let config = MatchConfig {
+        games: 8,
+        balanced_openings: true,
+        opening_turns: 3,
+        max_imbalance: 1.0,
+        ..Default::default()
+    };
+    for start in starts(&[(4, 3)]) {
+        let openings = balanced_openings(start, config.games / 2, &config);
+        assert_eq!(openings.len(), config.games / 2, "{}", start);
+        let mut table = TranspositionTable::default();
+        for (i, opening) in openings.iter().enumerate() {
+            assert!(!openings[..i].contains(opening));
+            let value = GameTree::new(*opening)
+                .search(config.opening_depth, &mut table)
+                .unwrap()
+                .value;
+            assert!(
+                value.abs() <= config.max_imbalance,
+                "{} is worth {}",
+                opening,
+                value
+            );
+        }
+
+        // both players give up at once, so each game ends on the first turn of its opening
+        let calls = Calls::default();
+        let mut first = Quitter::new("First", false, &calls);
+        let mut second = Quitter::new("Second", false, &calls);
+        let result = play_match(start, &mut first, &mut second, &config);
+        assert_eq!(result.games(), config.games);
+        let calls = calls.borrow();
+        assert_eq!(calls.len(), config.games);
+        for (opening, games) in openings.iter().zip(calls.chunks(2)) {
+            let [(mover, game, side), (other_mover, other_game, other_side)] = games else {
+                unreachable!();
+            };
+            assert!(game == opening && other_game == opening);
+            // the same side is to move, with each player in that seat once
+            assert_eq!(side, other_side);
+            assert_ne!(mover, other_mover);
+        }
+    }
+}
+
+#[test]
+fn round_robin_results_are_symmetric() {
+    let config = MatchConfig {
+        games: 4,
+        ..Default::default()
+    };
+    let start = Game::with_rules(Board::with_size(3, 3).unwrap(), Variant::Kalah.rules());
+    let mut players: Vec<Box<dyn Player>> = vec![
+        Box::new(RandomPlayer::new(1)),
+        Box::new(RandomPlayer::new(2)),
+        Box::new(MinimaxPlayer::new(1)),
+    ];
+    let table = round_robin(start, &mut players, &config);
+    let count = players.len();
+    assert_eq!(table.names.len(), count);
+    for i in 0..count {
+        assert!(table.results[i][i].is_none());
+        assert_eq!(table.points(i).1, (count - 1) * config.games);
+        for j in (0..count).filter(|&j| j != i) {
+            let ours = table.results[i][j].as_ref().unwrap();
+            let theirs = table.results[j][i].as_ref().unwrap();
+            assert_eq!(ours, &theirs.reversed());
+            assert_eq!(ours.games(), config.games);
+            assert_eq!(ours.score() + theirs.score(), 1.0);
+        }
+    }
+    let total = (0..count).map(|i| table.points(i).0).sum::<f64>();
+    assert_eq!(total, (count * (count - 1) / 2 * config.games) as f64);
+}
+
+#[test]
+fn crosstable_display() {
+    let result = MatchResult {
+        first: "A".to_string(),
+        second: "Bee".to_string(),
+        wins: 3,
+        draws: 1,
+        ..Default::default()
+    };
+    let table = Crosstable {
+        names: vec!["A".to_string(), "Bee".to_string()],
+        results: vec![
+            vec![None, Some(result.clone())],
+            vec![Some(result.reversed()), None],
+        ],
+    };
+    assert_eq!(
+        table.to_string(),
+        concat!(
+            "            1     2  Points\n",
+            "  1 A       - 3.5/4  3.5/4\n",
+            "  2 Bee 0.5/4     -  0.5/4\n",
+        )
+    );
+}
@@ -0,0 +1,78 @@
+use crate::game::{Game, PlayerSide};
+use crate::transposition::{Bound, TranspositionTable};
+
+/// Two different positions, which always share the slot of a table with one slot
+fn two_positions() -> (Game, Game) {
+    let first = Game::default();
+    let mut second = first;
+    second.play_move((0, PlayerSide::Player)).unwrap();
+    (first, second)
+}
+
+#[test]
+fn table_hit_returns_the_stored_entry() {
+    let (game, other) = two_positions();
+    let mut table = TranspositionTable::default();
+    table.new_search();
+    assert!(table.probe(&game).is_none());
+    table.store(game, 3, Bound::Lower, 7.5, vec![2, 5]);
+    let entry = table.probe(&game).unwrap();
+    assert_eq!(entry.game, game);
+    assert_eq!(entry.depth, 3);
+    assert_eq!(entry.bound, Bound::Lower);
+    assert_eq!(entry.value, 7.5);
+    assert_eq!(entry.best_turn, vec![2, 5]);
+    assert!(table.probe(&other).is_none());
+    // storing the same position again always overwrites it
+    table.store(game, 1, Bound::Exact, -2.0, vec![1]);
+    let entry = table.probe(&game).unwrap();
+    assert_eq!(
+        (entry.depth, entry.bound, entry.value),
+        (1, Bound::Exact, -2.0)
+    );
+}
+
+#[test]
+fn table_replaces_by_depth_and_generation() {
+    let (game, other) = two_positions();
+    let mut table = TranspositionTable::new(1);
+    table.new_search();
+    table.store(game, 3, Bound::Exact, 1.0, Vec::new());
+    // a shallower result from the same search doesn't replace a deeper one
+    table.store(other, 2, Bound::Exact, 2.0, Vec::new());
+    assert!(table.probe(&other).is_none());
+    assert_eq!(table.probe(&game).unwrap().value, 1.0);
+    // one searched at least as deep does
+    table.store(other, 3, Bound::Exact, 2.0, Vec::new());
+    assert!(table.probe(&game).is_none());
+    assert_eq!(table.probe(&other).unwrap().value, 2.0);
+    // entries from an earlier search are replaced by anything
+    table.new_search();
+    table.store(game, 1, Bound::Upper, 3.0, Vec::new());
+    assert!(table.probe(&other).is_none());
+    assert_eq!(table.probe(&game).unwrap().value, 3.0);
+    assert_eq!(table.stats.replacements, 2);
+}
+
+#[test]
+fn expanded_markers_are_not_search_results() {
+    let (game, other) = two_positions();
+    let mut table = TranspositionTable::new(1);
+    table.new_search();
+    assert!(!table.mark_expanded(&game));
+    assert!(table.mark_expanded(&game));
+    assert!(table.probe(&game).is_none());
+    // markers only last for the search they were made in
+    table.new_search();
+    assert!(!table.mark_expanded(&game));
+    // a marker doesn't evict a search result of the current search
+    table.store(other, 2, Bound::Exact, 4.0, Vec::new());
+    assert!(!table.mark_expanded(&game));
+    assert_eq!(table.probe(&other).unwrap().value, 4.0);
+    // but a search result replaces a marker
+    table.new_search();
+    assert!(!table.mark_expanded(&game));
+    table.store(game, 1, Bound::Exact, 5.0, Vec::new());
+    assert_eq!(table.probe(&game).unwrap().value, 5.0);
+    assert!(!table.mark_expanded(&game));
+}
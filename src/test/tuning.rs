@@ -0,0 +1,68 @@
+use crate::eval::{Feature, FeatureWeights};
+use crate::rng::Rng;
+use crate::tuning::{fit, tune, Sample, TuningConfig};
+
+use super::starts;
+
+/// Samples with random features, each labelled with the chance of a Player win that the given
+/// weights predict, so those weights fit them best. The last feature never changes.
+fn synthetic_samples(weights: [f32; 3], count: usize) -> Vec<Sample> {
+    let mut rng = Rng(9);
+    let mut uniform = |range: f32| (rng.below(2001) as f32 / 1000.0 - 1.0) * range;
+    (0..count)
+        .map(|_| {
+            let features = [uniform(10.0), uniform(8.0), uniform(5.0), 2.0];
+            let x: f32 = features.iter().zip(weights).map(|(f, w)| f * w).sum();
+            Sample {
+                features,
+                result: 1.0 / (1.0 + (-x).exp()),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn fit_recovers_the_weights_the_samples_were_made_with() {
+    assert_eq!(Feature::ALL.len(), 4);
+    let config = TuningConfig::default();
+    for weights in [[0.3, 0.15, -0.075], [0.2, -0.4, 0.1], [0.05, 0.0, 0.2]] {
+        let (fitted, loss) = fit(&synthetic_samples(weights, 2000), &config);
+        // only the ratios are fitted, with the store difference anchored at 1
+        assert_eq!(fitted.get(Feature::StoreDifference), 1.0);
+        for (feature, weight) in Feature::ALL[1..3].iter().zip(&weights[1..]) {
+            let expected = weight / weights[0];
+            assert!(
+                (fitted.get(*feature) - expected).abs() < 0.02 * expected.abs().max(1.0),
+                "{} weighted {} instead of {}",
+                feature,
+                fitted.get(*feature),
+                expected
+            );
+        }
+        // a feature that is the same in every sample can't predict anything
+        assert_eq!(fitted.get(Feature::FreeMovePotential), 0.0);
+        assert!(loss.is_finite() && loss > 0.0);
+    }
+}
+
+#[test]
+fn tune_is_reproducible() {
+    let config = TuningConfig {
+        games: 6,
+        depth: 1,
+        iterations: 200,
+        ..Default::default()
+    };
+    for start in starts(&[(3, 3)]) {
+        let first = tune(start, FeatureWeights::default(), &config);
+        let second = tune(start, FeatureWeights::default(), &config);
+        assert_eq!(first.weights, second.weights, "{}", start);
+        assert_eq!(first.positions, second.positions);
+        assert_eq!(first.loss.to_bits(), second.loss.to_bits());
+        assert!(first.positions > 0);
+        // the weights survive being saved and loaded again
+        let text = first.weights.to_string();
+        assert_eq!(text.parse::<FeatureWeights>(), Ok(first.weights));
+        assert_eq!(text.parse::<FeatureWeights>().unwrap().to_string(), text);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::game::{Board, Game, GameState};
+use crate::rng::Rng;
+use crate::rules::{Rules, Sweep, Variant};
+
+use super::playable;
+
+/// The board rebuilt from its pockets, so its Zobrist key is computed from scratch
+fn rebuilt(board: &Board) -> Board {
+    let store = board.store();
+    Board::free_form(
+        &board.player_pockets[..=store],
+        &board.opponent_pockets[..=store],
+        board.player_turn,
+    )
+    .unwrap()
+}
+
+/// Checks the incremental key of a board and of its mirror against keys computed from scratch
+fn assert_key_from_scratch(board: &Board) {
+    assert_eq!(
+        board.zobrist_key(),
+        rebuilt(board).zobrist_key(),
+        "{}",
+        board
+    );
+    let store = board.store();
+    let mirrored = Board::free_form(
+        &board.opponent_pockets[..=store],
+        &board.player_pockets[..=store],
+        board.mirrored().player_turn,
+    )
+    .unwrap();
+    assert_eq!(board.mirrored().zobrist_key(), mirrored.zobrist_key());
+    assert_eq!(
+        board.mirrored().mirrored().zobrist_key(),
+        board.zobrist_key()
+    );
+}
+
+#[test]
+fn incremental_zobrist_key_matches_a_fresh_key_through_whole_games() {
+    let mut rng = Rng(7);
+    let mut all_rules = Variant::ALL.map(Variant::rules).to_vec();
+    // the Game Pigeon rules with the leftover stones swept at the end
+    all_rules.push(Rules {
+        sweep: Sweep::ToOwner,
+        ..Variant::Avalanche.rules()
+    });
+    for rules in all_rules {
+        // the larger boards go past the stone counts the keys distinguish
+        for (pockets, stones) in [(6, 4), (3, 2), (4, 12)] {
+            for _ in 0..20 {
+                let mut game = Game::with_rules(Board::with_size(pockets, stones).unwrap(), rules);
+                assert_key_from_scratch(&game.board);
+                while game.game_state == GameState::InProgress {
+                    let side = game.board.player_turn;
+                    let playable = playable(&game);
+                    let pocket = playable[rng.below(playable.len())];
+                    // every step of a traced move carries a consistent key too
+                    let mut traced = game;
+                    for step in traced.play_move_traced((pocket, side)).unwrap() {
+                        assert_key_from_scratch(&step.board);
+                    }
+                    game.play_move((pocket, side)).unwrap();
+                    assert_eq!(game, traced);
+                    assert_key_from_scratch(&game.board);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use crate::game::{Game, GameOver, GameState, PlayerSide};
+use crate::minimax::GameTree;
+use crate::player::{Player, RandomPlayer};
+use crate::transposition::TranspositionTable;
+
+/*
+A match is a series of games between two players, who swap sides after every game. With balanced
+openings, each opening is played twice in a row, once with each player on each side, so neither
+player gets the better of an opening more often than the other.
+Results are counted from the first player's view. Games still going after `max_turns` turns count
+as draws, and a player who has no turn to play in a game that isn't over loses it.
+The Elo difference comes from the score (wins plus half the draws, over the games played):
+    elo = -400 * log10(1 / score - 1)
+and its error bars from the 95% confidence interval of the score, using the standard error of the
+results of the single games.
+*/
+
+/// Settings for `play_match` and `round_robin`
+#[derive(Debug, Clone, Copy)]
+pub struct MatchConfig {
+    /// Number of games each pair of players plays
+    pub games: usize,
+    /// Games that are still going after this many turns are stopped and counted as draws
+    pub max_turns: usize,
+    /// Whether to start the games from balanced openings instead of the starting position
+    pub balanced_openings: bool,
+    /// Number of turns played at random to reach each opening
+    pub opening_turns: usize,
+    /// Number of turns minimax searches ahead to check that an opening is balanced
+    pub opening_depth: usize,
+    /// The largest minimax value (in stones, either way) an opening can have to be balanced
+    pub max_imbalance: f32,
+    /// Seed for the random opening turns; the same seed always gives the same openings
+    pub seed: u64,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        MatchConfig {
+            games: 20,
+            max_turns: 200,
+            balanced_openings: false,
+            opening_turns: 2,
+            opening_depth: 2,
+            max_imbalance: 2.0,
+            seed: 1,
+        }
+    }
+}
+
+/// Positions reached by random turns from `start` that neither side is ahead in by more than
+/// `config.max_imbalance` when searched `config.opening_depth` turns ahead.
+/// Every opening is a different position. Fewer than `count` openings are returned if they can't
+/// be found, and none at all if `start` is already over.
+pub fn balanced_openings(start: Game, count: usize, config: &MatchConfig) -> Vec<Game> {
+    // each attempt plays its own random turns, so there is no need for more than a few per opening
+    const ATTEMPTS_PER_OPENING: usize = 50;
+    let mut random = RandomPlayer::new(config.seed);
+    let mut table = TranspositionTable::default();
+    let mut seen = HashSet::new();
+    let mut openings = Vec::new();
+    for _ in 0..count * ATTEMPTS_PER_OPENING {
+        if openings.len() == count {
+            break;
+        }
+        let mut game = start;
+        for _ in 0..config.opening_turns {
+            if game.game_state != GameState::InProgress {
+                break;
+            }
+            for pocket in random.choose_turn(&game).unwrap_or_default() {
+                if game.play_move((pocket, game.board.player_turn)).is_err() {
+                    break;
+                }
+            }
+        }
+        if game.game_state != GameState::InProgress || !seen.insert(game) {
+            continue;
+        }
+        let Ok(result) = GameTree::new(game).search(config.opening_depth, &mut table) else {
+            continue;
+        };
+        if result.value.abs() <= config.max_imbalance {
+            openings.push(game);
+        }
+    }
+    openings
+}
+
+/// The results of a match, from the first player's view
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MatchResult {
+    /// The name of the first player
+    pub first: String,
+    /// The name of the second player
+    pub second: String,
+    pub wins: usize,
+    pub losses: usize,
+    /// Ties, and games stopped after `max_turns`
+    pub draws: usize,
+    /// The wins that were technical wins, where the first player was ahead by more than the
+    /// stones left on the board
+    pub technical_wins: usize,
+    /// The losses that were technical wins for the second player
+    pub technical_losses: usize,
+    /// The sum over every game of the first player's store minus the second player's
+    pub total_margin: i64,
+}
+
+impl MatchResult {
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+
+    /// The first player's share of the points: 1 for a win and 0.5 for a draw, over the games
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games().max(1) as f64
+    }
+
+    /// The first player's average lead in stones at the end of a game
+    pub fn average_margin(&self) -> f64 {
+        self.total_margin as f64 / self.games().max(1) as f64
+    }
+
+    /// The first player's rating minus the second player's, estimated from the score.
+    /// Infinite if one player won every game.
+    pub fn elo(&self) -> f64 {
+        elo_difference(self.score())
+    }
+
+    /// Half the width of the 95% confidence interval of `elo`. Infinite when the interval
+    /// reaches a score of 0 or 1, which always happens if one player won every game.
+    pub fn elo_error(&self) -> f64 {
+        let games = self.games().max(1) as f64;
+        let score = self.score();
+        let variance = (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games;
+        let margin = 1.96 * (variance / games).sqrt();
+        if score - margin <= 0.0 || score + margin >= 1.0 {
+            return f64::INFINITY;
+        }
+        (elo_difference(score + margin) - elo_difference(score - margin)) / 2.0
+    }
+
+    /// The same match from the second player's view
+    pub fn reversed(&self) -> Self {
+        MatchResult {
+            first: self.second.clone(),
+            second: self.first.clone(),
+            wins: self.losses,
+            losses: self.wins,
+            draws: self.draws,
+            technical_wins: self.technical_losses,
+            technical_losses: self.technical_wins,
+            total_margin: -self.total_margin,
+        }
+    }
+}
+
+fn elo_difference(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+impl Display for MatchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} vs {}: {} wins, {} losses, {} draws ({} technical wins, {} technical losses)",
+            self.first,
+            self.second,
+            self.wins,
+            self.losses,
+            self.draws,
+            self.technical_wins,
+            self.technical_losses
+        )?;
+        write!(
+            f,
+            "Score {:.1}%, average margin {:+.1}, Elo {:+.0} ± {:.0}",
+            self.score() * 100.0,
+            self.average_margin(),
+            self.elo(),
+            self.elo_error()
+        )
+    }
+}
+
+/// Plays one game from `start` with `first` playing `first_side`, adding its result to `result`
+fn play_game(
+    start: Game,
+    first: &mut dyn Player,
+    second: &mut dyn Player,
+    first_side: PlayerSide,
+    max_turns: usize,
+    result: &mut MatchResult,
+) {
+    let mut game = start;
+    // the side that gave up, if either did
+    let mut forfeit = None;
+    for _ in 0..max_turns {
+        if game.game_state != GameState::InProgress {
+            break;
+        }
+        let side = game.board.player_turn;
+        let sequence = match side == first_side {
+            true => first.choose_turn(&game),
+            false => second.choose_turn(&game),
+        };
+        // a player with no turn to play in a game that isn't over gives it up
+        let Ok(sequence) = sequence else {
+            forfeit = Some(side);
+            break;
+        };
+        for pocket in sequence {
+            if game.play_move((pocket, side)).is_err() {
+                // an illegal turn gives the game up too
+                forfeit = Some(side);
+                break;
+            }
+        }
+        if forfeit.is_some() {
+            break;
+        }
+    }
+    let second_side = match first_side {
+        PlayerSide::Player => PlayerSide::Opponent,
+        PlayerSide::Opponent => PlayerSide::Player,
+    };
+    result.total_margin += (game.board.score(first_side) - game.board.score(second_side)) as i64;
+    let first_result = match forfeit {
+        Some(side) => Some(if side == first_side { 0.0 } else { 1.0 }),
+        None => game.player_result().map(|result| match first_side {
+            PlayerSide::Player => result,
+            PlayerSide::Opponent => 1.0 - result,
+        }),
+    };
+    match first_result {
+        Some(score) if score > 0.5 => result.wins += 1,
+        Some(score) if score < 0.5 => result.losses += 1,
+        _ => result.draws += 1,
+    }
+    if forfeit.is_none() {
+        if let GameState::Over(GameOver::TechnicalWin(winner)) = game.game_state {
+            match winner == first_side {
+                true => result.technical_wins += 1,
+                false => result.technical_losses += 1,
+            }
+        }
+    }
+}
+
+/// Plays `config.games` games between `first` and `second`, swapping sides after every game.
+/// The games start from `openings` in turn, each played twice, so there must be at least one.
+fn play_games(
+    openings: &[Game],
+    first: &mut dyn Player,
+    second: &mut dyn Player,
+    config: &MatchConfig,
+) -> MatchResult {
+    let mut result = MatchResult {
+        first: first.name(),
+        second: second.name(),
+        ..Default::default()
+    };
+    for game in 0..config.games {
+        let start = openings[(game / 2) % openings.len()];
+        let first_side = match game % 2 {
+            0 => PlayerSide::Player,
+            _ => PlayerSide::Opponent,
+        };
+        play_game(
+            start,
+            first,
+            second,
+            first_side,
+            config.max_turns,
+            &mut result,
+        );
+    }
+    result
+}
+
+/// The positions the games of a match from `start` are played from. If no balanced openings can
+/// be found, the games are played from `start`.
+fn match_openings(start: Game, config: &MatchConfig) -> Vec<Game> {
+    let openings = match config.balanced_openings {
+        true => balanced_openings(start, config.games.div_ceil(2), config),
+        false => Vec::new(),
+    };
+    match openings.is_empty() {
+        true => vec![start],
+        false => openings,
+    }
+}
+
+/// Plays a match of `config.games` games between `first` and `second` from `start`, or from
+/// balanced openings reached from `start`. The players swap sides after every game, so with an
+/// even number of games each plays every opening from both sides.
+pub fn play_match(
+    start: Game,
+    first: &mut dyn Player,
+    second: &mut dyn Player,
+    config: &MatchConfig,
+) -> MatchResult {
+    play_games(&match_openings(start, config), first, second, config)
+}
+
+/// The results of a round-robin tournament
+#[derive(Debug, Clone)]
+pub struct Crosstable {
+    /// The name of each player, in the order they were entered
+    pub names: Vec<String>,
+    /// The match between players i and j from player i's view in `results[i][j]`, and None on
+    /// the diagonal
+    pub results: Vec<Vec<Option<MatchResult>>>,
+}
+
+impl Crosstable {
+    /// The points player i scored over all of their matches, and the number of games they played
+    pub fn points(&self, i: usize) -> (f64, usize) {
+        self.results[i]
+            .iter()
+            .flatten()
+            .fold((0.0, 0), |(points, games), result| {
+                (
+                    points + result.wins as f64 + 0.5 * result.draws as f64,
+                    games + result.games(),
+                )
+            })
+    }
+}
+
+impl Display for Crosstable {
+    /// A table with each player's points against every other player, and their total
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name_width = self.names.iter().map(|name| name.len()).max().unwrap_or(0);
+        let cell = |result: &Option<MatchResult>| match result {
+            Some(result) => format!(
+                "{}/{}",
+                result.wins as f64 + 0.5 * result.draws as f64,
+                result.games()
+            ),
+            None => "-".to_string(),
+        };
+        let cell_width = self
+            .results
+            .iter()
+            .flatten()
+            .map(|result| cell(result).len())
+            .max()
+            .unwrap_or(1)
+            .max(self.names.len().to_string().len());
+        write!(f, "{:>3} {:<name_width$}", "", "")?;
+        for j in 0..self.names.len() {
+            write!(f, " {:>cell_width$}", j + 1)?;
+        }
+        writeln!(f, "  Points")?;
+        for (i, name) in self.names.iter().enumerate() {
+            write!(f, "{:>3} {:<name_width$}", i + 1, name)?;
+            for result in &self.results[i] {
+                write!(f, " {:>cell_width$}", cell(result))?;
+            }
+            let (points, games) = self.points(i);
+            writeln!(f, "  {}/{}", points, games)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plays a match between every pair of players, all from the same openings, and returns the
+/// crosstable of the results
+pub fn round_robin(
+    start: Game,
+    players: &mut [Box<dyn Player>],
+    config: &MatchConfig,
+) -> Crosstable {
+    let openings = match_openings(start, config);
+    let count = players.len();
+    let mut matches = Vec::new();
+    for j in 1..count {
+        // players before j are on the left, so both can be borrowed mutably
+        let (left, right) = players.split_at_mut(j);
+        for (i, player) in left.iter_mut().enumerate() {
+            let result = play_games(&openings, player.as_mut(), right[0].as_mut(), config);
+            matches.push((i, j, result));
+        }
+    }
+    let mut results = vec![vec![None; count]; count];
+    for (i, j, result) in matches {
+        results[j][i] = Some(result.reversed());
+        results[i][j] = Some(result);
+    }
+    Crosstable {
+        names: players.iter().map(|player| player.name()).collect(),
+        results,
+    }
+}
@@ -0,0 +1,211 @@
+use crate::game::{Game, PocketIndex};
+
+/// Default number of slots in a transposition table
+const DEFAULT_CAPACITY: usize = 1 << 16;
+
+/// How the stored value relates to the true minimax value of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The value is the exact minimax value
+    Exact,
+    /// The search was cut off, the true value is at least the stored value
+    Lower,
+    /// No turn reached alpha, the true value is at most the stored value
+    Upper,
+}
+
+/// A search result cached for a position
+#[derive(Debug, Clone)]
+pub struct TableEntry {
+    /// The position the entry belongs to, used to detect slot collisions
+    pub game: Game,
+    /// How many turns were searched below the position
+    pub depth: usize,
+    /// How the value relates to the true minimax value
+    pub bound: Bound,
+    /// The value found by the search (positive favors the Player)
+    pub value: f32,
+    /// The best turn found for the player to move
+    pub best_turn: Vec<PocketIndex>,
+    /// The search the entry was stored in
+    generation: u32,
+}
+
+/// What a slot of the table holds
+#[derive(Debug, Clone)]
+enum Slot {
+    /// A minimax search result
+    Search(TableEntry),
+    /// A position a sequence tree has already expanded during the search of `generation`.
+    /// It has no value, so it is never returned by `probe`.
+    Expanded { game: Game, generation: u32 },
+}
+
+impl Slot {
+    fn game(&self) -> &Game {
+        match self {
+            Slot::Search(entry) => &entry.game,
+            Slot::Expanded { game, .. } => game,
+        }
+    }
+
+    fn generation(&self) -> u32 {
+        match self {
+            Slot::Search(entry) => entry.generation,
+            Slot::Expanded { generation, .. } => *generation,
+        }
+    }
+
+    /// Expanded markers count as searched 0 turns deep
+    fn depth(&self) -> usize {
+        match self {
+            Slot::Search(entry) => entry.depth,
+            Slot::Expanded { .. } => 0,
+        }
+    }
+}
+
+/// Counters describing how the table has been used
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TableStats {
+    /// Number of lookups
+    pub probes: usize,
+    /// Number of lookups that found the position
+    pub hits: usize,
+    /// Number of entries written
+    pub stores: usize,
+    /// Number of stores that overwrote a different position
+    pub replacements: usize,
+}
+
+/// Fixed size cache of search results keyed on `Game`.
+///
+/// Each position maps to a single slot. When two positions compete for a slot the new entry only
+/// replaces the old one if the old entry is from an earlier search or was searched no deeper.
+/// Besides search results, a slot can mark a position a sequence tree has already expanded, which
+/// only lasts for the current search and never reads as a value.
+pub struct TranspositionTable {
+    slots: Vec<Option<Slot>>,
+    /// Incremented at the start of every search so entries from older searches can be replaced first
+    generation: u32,
+    pub stats: TableStats,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl TableEntry {
+    /// The search the entry was stored in
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl TranspositionTable {
+    pub fn new(capacity: usize) -> Self {
+        TranspositionTable {
+            slots: vec![None; capacity.max(1)],
+            generation: 0,
+            stats: TableStats::default(),
+        }
+    }
+
+    /// Start a new search; entries stored before this call become the first to be replaced
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// The generation of the current search
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    fn slot(&self, game: &Game) -> usize {
+        (game.board.zobrist_key() % self.slots.len() as u64) as usize
+    }
+
+    /// Look up the search result stored for the given position
+    pub fn probe(&mut self, game: &Game) -> Option<&TableEntry> {
+        self.stats.probes += 1;
+        let slot = self.slot(game);
+        match self.slots[slot] {
+            Some(Slot::Search(ref entry)) if entry.game == *game => {
+                self.stats.hits += 1;
+                Some(entry)
+            }
+            _ => None,
+        }
+    }
+
+    /// Put `new` in the slot of its position if the replacement policy allows it
+    fn replace(&mut self, new: Slot) {
+        let slot = self.slot(new.game());
+        if let Some(ref old) = self.slots[slot] {
+            let same_position = old.game() == new.game();
+            let replaceable = old.generation() != self.generation || new.depth() >= old.depth();
+            if !same_position && !replaceable {
+                return;
+            }
+            if !same_position {
+                self.stats.replacements += 1;
+            }
+        }
+        self.stats.stores += 1;
+        self.slots[slot] = Some(new);
+    }
+
+    /// Store a search result for the given position, following the table's replacement policy
+    pub fn store(
+        &mut self,
+        game: Game,
+        depth: usize,
+        bound: Bound,
+        value: f32,
+        best_turn: Vec<PocketIndex>,
+    ) {
+        self.replace(Slot::Search(TableEntry {
+            game,
+            depth,
+            bound,
+            value,
+            best_turn,
+            generation: self.generation,
+        }));
+    }
+
+    /// Whether a sequence tree already expanded the position during the current search.
+    /// If it didn't, the position is marked as expanded (unless a deeper search result of this
+    /// search holds its slot) and false is returned.
+    pub fn mark_expanded(&mut self, game: &Game) -> bool {
+        self.stats.probes += 1;
+        let slot = self.slot(game);
+        if let Some(Slot::Expanded {
+            game: ref marked,
+            generation,
+        }) = self.slots[slot]
+        {
+            if marked == game && generation == self.generation {
+                self.stats.hits += 1;
+                return true;
+            }
+        }
+        self.replace(Slot::Expanded {
+            game: *game,
+            generation: self.generation,
+        });
+        false
+    }
+
+    /// Number of slots in the table
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Number of slots holding an entry
+    pub fn used_slots(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+}
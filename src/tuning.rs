@@ -0,0 +1,224 @@
+use crate::eval::{Feature, FeatureWeights};
+use crate::game::{Game, GameState, PocketIndex};
+use crate::minimax::GameTree;
+use crate::rng::Rng;
+use crate::solver::SequenceTree;
+use crate::transposition::TranspositionTable;
+
+/*
+Evaluator weights are tuned Texel style: the engine plays itself, every position reached after the
+random opening is labelled with the result of its game, and the weights are fit with logistic
+regression so that sigmoid(evaluation) predicts the result.
+1.0 is a Player win, 0.0 an Opponent win and 0.5 a tie. Only the ratio between the weights changes
+which turn a search picks, so the fitted weights are scaled to keep the store difference at 1.
+*/
+
+/// Games that are still going after this many turns are dropped, since their result is unknown
+const MAX_TURNS: usize = 200;
+
+/// Settings for `tune`
+#[derive(Debug, Clone, Copy)]
+pub struct TuningConfig {
+    /// Number of self-play games to play
+    pub games: usize,
+    /// Number of turns each side searches ahead during self-play
+    pub depth: usize,
+    /// Number of turns played at random at the start of each game, so that the games differ
+    pub random_turns: usize,
+    /// Number of gradient descent steps when fitting the weights
+    pub iterations: usize,
+    /// Step size of gradient descent, in units of each feature's standard deviation
+    pub learning_rate: f32,
+    /// Seed for the random openings; the same seed and settings always give the same weights
+    pub seed: u64,
+}
+
+impl Default for TuningConfig {
+    fn default() -> Self {
+        TuningConfig {
+            games: 500,
+            depth: 2,
+            random_turns: 2,
+            iterations: 2000,
+            learning_rate: 0.5,
+            seed: 1,
+        }
+    }
+}
+
+/// A position from a self-play game and the result of that game
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// The value of each feature, in the order of `Feature::ALL`
+    pub features: [f32; Feature::ALL.len()],
+    /// 1.0 if the Player won, 0.0 if the Opponent won and 0.5 for a tie
+    pub result: f32,
+}
+
+/// The weights found by `tune`
+#[derive(Debug, Clone, Copy)]
+pub struct TuningResult {
+    pub weights: FeatureWeights,
+    /// Number of positions the weights were fit to
+    pub positions: usize,
+    /// Mean log loss of the fitted weights' predictions
+    pub loss: f32,
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+/// A random turn for the player to move, or None if they have no legal turn
+fn random_turn(game: &Game, rng: &mut Rng) -> Option<Vec<PocketIndex>> {
+    let mut tree = SequenceTree::new(*game);
+    tree.generate_tree(game.board.player_turn, None);
+    if tree.leaf_nodes.is_empty() {
+        return None;
+    }
+    let leaf = tree.leaf_nodes[rng.below(tree.leaf_nodes.len())];
+    tree.get_move_sequence(leaf).ok()
+}
+
+/// Plays `config.games` games from `start` with both sides searching with `weights`, and returns
+/// every position after the random opening labelled with its game's result
+pub fn self_play(start: Game, weights: FeatureWeights, config: &TuningConfig) -> Vec<Sample> {
+    let mut rng = Rng(config.seed);
+    let mut table = TranspositionTable::default();
+    let mut samples = Vec::new();
+    for _ in 0..config.games {
+        let mut game = start;
+        let mut positions = Vec::new();
+        for turn in 0..MAX_TURNS {
+            if game.game_state != GameState::InProgress {
+                break;
+            }
+            let sequence = match turn < config.random_turns {
+                true => random_turn(&game, &mut rng),
+                false => {
+                    positions.push(FeatureWeights::features(&game));
+                    GameTree::with_evaluator(game, weights)
+                        .search(config.depth, &mut table)
+                        .ok()
+                        .map(|result| result.sequence)
+                }
+            };
+            let Some(sequence) = sequence else {
+                break;
+            };
+            for pocket in sequence {
+                // every turn comes from the sequence tree, so it is always legal
+                if game.play_move((pocket, game.board.player_turn)).is_err() {
+                    break;
+                }
+            }
+        }
+        let Some(result) = game.player_result() else {
+            continue;
+        };
+        samples.extend(
+            positions
+                .into_iter()
+                .map(|features| Sample { features, result }),
+        );
+    }
+    samples
+}
+
+/// Mean log loss of predicting each sample's result with sigmoid(weights . features)
+fn log_loss(samples: &[Sample], weights: &[f32]) -> f32 {
+    let total: f32 = samples
+        .iter()
+        .map(|sample| {
+            let x: f32 = sample
+                .features
+                .iter()
+                .zip(weights)
+                .map(|(f, w)| f * w)
+                .sum();
+            let p = sigmoid(x).clamp(1e-6, 1.0 - 1e-6);
+            -(sample.result * p.ln() + (1.0 - sample.result) * (1.0 - p).ln())
+        })
+        .sum();
+    total / samples.len().max(1) as f32
+}
+
+/// Fits feature weights to the samples with logistic regression, returning the weights and their
+/// mean log loss. Features that never change are left at 0.
+pub fn fit(samples: &[Sample], config: &TuningConfig) -> (FeatureWeights, f32) {
+    const FEATURES: usize = Feature::ALL.len();
+    let n = samples.len().max(1) as f32;
+    // every feature is scaled to a standard deviation of 1 so that one learning rate suits them all
+    let mut scale = [0.0; FEATURES];
+    for (i, scale) in scale.iter_mut().enumerate() {
+        let mean = samples.iter().map(|sample| sample.features[i]).sum::<f32>() / n;
+        let variance = samples
+            .iter()
+            .map(|sample| (sample.features[i] - mean).powi(2))
+            .sum::<f32>()
+            / n;
+        *scale = variance.sqrt();
+    }
+    let scaled = samples
+        .iter()
+        .map(|sample| {
+            let mut features = [0.0; FEATURES];
+            for i in 0..FEATURES {
+                if scale[i] > 0.0 {
+                    features[i] = sample.features[i] / scale[i];
+                }
+            }
+            Sample {
+                features,
+                result: sample.result,
+            }
+        })
+        .collect::<Vec<Sample>>();
+    let mut weights = [0.0; FEATURES];
+    for _ in 0..config.iterations {
+        let mut gradient = [0.0; FEATURES];
+        for sample in &scaled {
+            let x: f32 = sample
+                .features
+                .iter()
+                .zip(weights)
+                .map(|(f, w)| f * w)
+                .sum();
+            let error = sigmoid(x) - sample.result;
+            for (gradient, feature) in gradient.iter_mut().zip(sample.features) {
+                *gradient += error * feature;
+            }
+        }
+        for i in 0..FEATURES {
+            weights[i] -= config.learning_rate * gradient[i] / n;
+        }
+    }
+    let loss = log_loss(&scaled, &weights);
+    // undo the scaling, then keep the store difference at 1 since only the ratios matter
+    for i in 0..FEATURES {
+        if scale[i] > 0.0 {
+            weights[i] /= scale[i];
+        }
+    }
+    let anchor = weights[Feature::StoreDifference as usize];
+    let mut fitted = FeatureWeights::zero();
+    for feature in Feature::ALL {
+        let weight = weights[feature as usize];
+        match anchor > 0.0 {
+            true => fitted.set(feature, weight / anchor),
+            false => fitted.set(feature, weight),
+        }
+    }
+    (fitted, loss)
+}
+
+/// Plays self-play games from `start` with `weights` and fits new weights to their results
+pub fn tune(start: Game, weights: FeatureWeights, config: &TuningConfig) -> TuningResult {
+    let samples = self_play(start, weights, config);
+    let (weights, loss) = fit(&samples, config);
+    TuningResult {
+        weights,
+        positions: samples.len(),
+        loss,
+    }
+}
@@ -0,0 +1,48 @@
+use crate::game::{PlayerSide, PocketLocation, MAX_POCKETS};
+use crate::rng::splitmix64;
+
+/// Stone counts above this share the same key; boards that only differ above the cap collide,
+/// which is fine since every user of the key also compares the boards themselves
+pub const MAX_ZOBRIST_STONES: usize = 48;
+
+/// Number of pockets on each side of the largest board, including the store
+const POCKETS_PER_SIDE: usize = MAX_POCKETS + 1;
+
+/// Seed for the key tables, any value works as long as it never changes between builds
+const SEED: u64 = 0x6d61_6e63_616c_6121;
+
+const fn generate_keys() -> ([[u64; MAX_ZOBRIST_STONES + 1]; 2 * POCKETS_PER_SIDE], u64) {
+    let mut keys = [[0; MAX_ZOBRIST_STONES + 1]; 2 * POCKETS_PER_SIDE];
+    let mut state = SEED;
+    let mut pocket = 0;
+    while pocket < 2 * POCKETS_PER_SIDE {
+        // An empty pocket contributes nothing to the key, so index 0 is left as 0
+        let mut stones = 1;
+        while stones <= MAX_ZOBRIST_STONES {
+            let (next_state, key) = splitmix64(state);
+            state = next_state;
+            keys[pocket][stones] = key;
+            stones += 1;
+        }
+        pocket += 1;
+    }
+    let (_, opponent_turn_key) = splitmix64(state);
+    (keys, opponent_turn_key)
+}
+
+const KEYS: ([[u64; MAX_ZOBRIST_STONES + 1]; 2 * POCKETS_PER_SIDE], u64) = generate_keys();
+
+/// The key of a pocket holding the given number of stones
+pub fn pocket_key(pocket: PocketLocation, stones: i32) -> u64 {
+    let side_offset = match pocket.1 {
+        PlayerSide::Player => 0,
+        PlayerSide::Opponent => POCKETS_PER_SIDE,
+    };
+    let stones = (stones.max(0) as usize).min(MAX_ZOBRIST_STONES);
+    KEYS.0[side_offset + pocket.0][stones]
+}
+
+/// The key toggled whenever the turn switches; it is part of the key while it's the Opponent's turn
+pub fn opponent_turn_key() -> u64 {
+    KEYS.1
+}